@@ -0,0 +1,175 @@
+//! Compares the alignment matcher with the greedy scorer it replaced, and
+//! batch scoring with a plain sequential scan.
+//!
+//! Scores every name of the sync databases (`pacman -Slq`) when pacman is
+//! available, otherwise the sample in `benches/names.txt`.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use std::process::Command;
+
+// Only part of the module's API is exercised here.
+#[allow(dead_code)]
+#[path = "../src/fuzzy/mod.rs"]
+mod fuzzy;
+
+/// The greedy first-occurrence scorer trx used before the alignment matcher.
+mod legacy {
+    pub fn fuzzy_match(query: &str, target: &str) -> f64 {
+        if query.is_empty() {
+            return 0.0;
+        }
+
+        let query_lower: Vec<char> = query.to_lowercase().chars().collect();
+        let target_lower: Vec<char> = target.to_lowercase().chars().collect();
+
+        let indices = match fuzzy_get_indexes(&query_lower, &target_lower) {
+            Some(i) => i,
+            None => return 0.0,
+        };
+
+        if indices.len() != query_lower.len() {
+            return 0.0;
+        }
+
+        calculate_score(&query_lower, &target_lower, &indices)
+    }
+
+    fn fuzzy_get_indexes(query: &[char], target: &[char]) -> Option<Vec<usize>> {
+        let mut indices = Vec::with_capacity(query.len());
+        let mut target_idx = 0;
+
+        for &query_char in query {
+            let pos = target[target_idx..].iter().position(|&c| c == query_char)?;
+            indices.push(target_idx + pos);
+            target_idx += pos + 1;
+        }
+
+        Some(indices)
+    }
+
+    fn calculate_score(query: &[char], target: &[char], indices: &[usize]) -> f64 {
+        let query_len = query.len() as f64;
+        let target_len = target.len() as f64;
+
+        let match_ratio = query_len / target_len.max(1.0);
+        let first_match_bonus = 1.0 - (indices[0] as f64 / target_len.max(1.0));
+
+        let consecutive_count = indices.windows(2).filter(|w| w[1] == w[0] + 1).count();
+        let consecutiveness = consecutive_count as f64 / query_len.max(1.0);
+
+        let span = (indices[indices.len() - 1] - indices[0] + 1) as f64;
+        let compactness = query_len / span;
+
+        let word_boundary_bonus = if indices[0] == 0 {
+            0.8
+        } else if matches!(target[indices[0] - 1], ' ' | '-' | '_' | '/') {
+            0.5
+        } else {
+            0.0
+        };
+
+        match_ratio * 0.3
+            + first_match_bonus * 0.2
+            + consecutiveness * 0.3
+            + compactness * 0.1
+            + word_boundary_bonus * 0.1
+    }
+}
+
+fn package_names() -> Vec<String> {
+    if let Ok(output) = Command::new("pacman").arg("-Slq").output()
+        && output.status.success()
+    {
+        return String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect();
+    }
+
+    include_str!("names.txt")
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn bench_matchers(c: &mut Criterion) {
+    let names = package_names();
+    let mut group = c.benchmark_group("fuzzy_match");
+
+    for query in ["vim", "pyreq", "libxml", "gstplugood"] {
+        group.bench_with_input(BenchmarkId::new("alignment", query), query, |b, q| {
+            b.iter(|| {
+                names
+                    .iter()
+                    .map(|name| fuzzy::fuzzy_match(black_box(q), name))
+                    .sum::<f64>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("greedy", query), query, |b, q| {
+            b.iter(|| {
+                names
+                    .iter()
+                    .map(|name| legacy::fuzzy_match(black_box(q), name))
+                    .sum::<f64>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_typos(c: &mut Criterion) {
+    let names = package_names();
+
+    c.bench_function("typo_match/firfeox", |b| {
+        b.iter(|| {
+            names
+                .iter()
+                .filter_map(|name| fuzzy::typo_match(black_box("firfeox"), name))
+                .count()
+        })
+    });
+}
+
+fn bench_batch(c: &mut Criterion) {
+    // The bundled sample is small; repeat it to the size of the sync databases.
+    let sample = package_names();
+    let names: Vec<String> = sample
+        .iter()
+        .cycle()
+        .take(sample.len().max(15_000))
+        .cloned()
+        .collect();
+    let mut group = c.benchmark_group("batch");
+
+    group.bench_function("sequential_sort", |b| {
+        b.iter(|| {
+            let mut scored: Vec<(usize, f64)> = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (i, fuzzy::fuzzy_match(black_box("pyth"), name)))
+                .filter(|(_, score)| *score > 0.0)
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored.truncate(50);
+            scored
+        })
+    });
+    group.bench_function("score_batch", |b| {
+        b.iter(|| fuzzy::score_batch(black_box("pyth"), &names, 50))
+    });
+    // Typing `pyth` one character at a time, narrowing after the first key.
+    group.bench_function("incremental_typing", |b| {
+        b.iter(|| {
+            let mut matcher = fuzzy::IncrementalMatcher::default();
+            for query in ["p", "py", "pyt", "pyth"] {
+                black_box(matcher.search("names", query, &names, 50));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_matchers, bench_typos, bench_batch);
+criterion_main!(benches);
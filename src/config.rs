@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// User settings read from `$XDG_CONFIG_HOME/trx/config.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Providers in order of preference when a package is offered by several.
+    pub provider_priority: Vec<String>,
+    pub ranking: RankingWeights,
+    /// Match `e` in a query against `é`, `è`, `ê` and so on.
+    pub ignore_diacritics: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            provider_priority: vec!["pacman".to_string(), "aur".to_string()],
+            ranking: RankingWeights::default(),
+            ignore_diacritics: true,
+        }
+    }
+}
+
+/// How much each signal adds to a search result's score, read from `[ranking]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// The fuzzy match against the query.
+    #[serde(rename = "match")]
+    pub matching: f64,
+    /// AUR votes.
+    pub votes: f64,
+    /// AUR popularity.
+    pub popularity: f64,
+    /// Already installed on this system.
+    pub installed: f64,
+    /// Installed through trx before.
+    pub history: f64,
+    /// Put in the basket before.
+    pub selections: f64,
+    /// Comes from the official repositories.
+    pub official: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            matching: 1.0,
+            votes: 0.05,
+            popularity: 0.05,
+            installed: 0.05,
+            history: 0.1,
+            selections: 0.1,
+            official: 0.05,
+        }
+    }
+}
+
+impl Config {
+    /// Position of `provider` in the priority list; unlisted providers come last.
+    pub fn provider_rank(&self, provider: &str) -> usize {
+        self.provider_priority
+            .iter()
+            .position(|p| p == provider)
+            .unwrap_or(self.provider_priority.len())
+    }
+}
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Reads the config from `dir` rather than the user's own; only the first
+/// call, made before the config is first read, takes effect.
+#[cfg(test)]
+pub fn use_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR.set(dir);
+}
+
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = CONFIG_DIR.get() {
+        return dir.clone();
+    }
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("trx");
+    }
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/trx")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Reads the config file, falling back to defaults when it is missing or invalid.
+pub fn load() -> Config {
+    fs::read_to_string(config_path())
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIG: Config = load();
+}
+
+pub fn get() -> &'static Config {
+    &CONFIG
+}
@@ -1,86 +1,587 @@
-pub fn fuzzy_match(query: &str, target: &str) -> f64 {
-    if query.is_empty() {
-        return 0.0;
+//! Optimal-alignment fuzzy matching in the style of fzf's v2 algorithm.
+//!
+//! Every way of embedding the query in the target is considered (a
+//! Smith-Waterman style dynamic program), so a later boundary or consecutive
+//! run is preferred over the first occurrence of each character.
+
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(test)]
+mod tests;
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL: i32 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+static IGNORE_DIACRITICS: AtomicBool = AtomicBool::new(true);
+
+/// Whether `e` in a query matches `é`, `è` or `ê`. On by default.
+pub fn set_ignore_diacritics(ignore: bool) {
+    IGNORE_DIACRITICS.store(ignore, AtomicOrdering::Relaxed);
+}
+
+/// Folds one character for comparison: case-folded and, when diacritics are
+/// ignored, reduced to its base letter. Always yields exactly one character,
+/// so match positions still index the original text.
+pub fn fold_char(c: char) -> char {
+    let mut base = c;
+    if !c.is_ascii() && IGNORE_DIACRITICS.load(AtomicOrdering::Relaxed) {
+        // Canonical decomposition puts the base letter first: `é` is `e` + U+0301.
+        let mut first = None;
+        decompose_canonical(c, |d| {
+            first.get_or_insert(d);
+        });
+        base = first.unwrap_or(c);
     }
+    match base {
+        // Final sigma folds like the medial one.
+        'ς' => 'σ',
+        _ => base.to_lowercase().next().unwrap_or(base),
+    }
+}
 
-    let query_lower: Vec<char> = query.to_lowercase().chars().collect();
-    let target_lower: Vec<char> = target.to_lowercase().chars().collect();
+/// The characters of `query` normalized (NFC) and folded.
+fn fold_chars(query: &str) -> impl Iterator<Item = char> + '_ {
+    let ignore = IGNORE_DIACRITICS.load(AtomicOrdering::Relaxed);
+    query
+        .nfc()
+        .filter(move |&c| !(ignore && is_combining_mark(c)))
+        .map(fold_char)
+}
 
-    let indices = match fuzzy_get_indexes(&query_lower, &target_lower) {
-        Some(i) => i,
-        None => return 0.0,
-    };
+/// Normalizes (NFC) and folds a query, so precomposed and decomposed input match alike.
+pub fn fold(query: &str) -> String {
+    fold_chars(query).collect()
+}
 
-    if indices.len() != query_lower.len() {
-        return 0.0;
+/// Fills `chars` with `target` normalized like a query, but not yet folded,
+/// and `origin` with the position in `target` each character came from, so
+/// match positions can be mapped back to the original name.
+pub fn normalize_target(target: &str, chars: &mut Vec<char>, origin: &mut Vec<usize>) {
+    chars.clear();
+    origin.clear();
+    if target.is_ascii() {
+        chars.extend(target.chars());
+        origin.extend(0..target.len());
+        return;
     }
 
-    calculate_score(&query_lower, &target_lower, &indices)
+    let ignore = IGNORE_DIACRITICS.load(AtomicOrdering::Relaxed);
+    let mut position = 0;
+    // Graphemes never compose with each other, so each normalizes alone.
+    for grapheme in target.graphemes(true) {
+        for c in grapheme
+            .nfc()
+            .filter(|&c| !(ignore && is_combining_mark(c)))
+        {
+            chars.push(c);
+            origin.push(position);
+        }
+        position += grapheme.chars().count();
+    }
+}
+
+/// Positions in the original target of the normalized positions `indices`.
+pub fn original_positions(indices: &[usize], origin: &[usize]) -> Vec<usize> {
+    let mut positions: Vec<usize> = indices.iter().map(|&i| origin[i]).collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    NonWord,
+    Lower,
+    Upper,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        // Scripts without case still form words.
+        CharClass::Lower
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// Bonus for matching a character of class `class` that follows one of class `prev`.
+fn position_bonus(prev: CharClass, class: CharClass) -> i32 {
+    match (prev, class) {
+        (_, CharClass::NonWord) => BONUS_NON_WORD,
+        (CharClass::NonWord, _) => BONUS_BOUNDARY,
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+        (CharClass::Lower | CharClass::Upper, CharClass::Number) => BONUS_CAMEL,
+        _ => 0,
+    }
 }
-pub fn fuzzy_get_indexes(query: &[char], target: &[char]) -> Option<Vec<usize>> {
-    let mut indices = Vec::with_capacity(query.len());
-    let mut target_idx = 0;
 
-    for &query_char in query {
-        let found = target[target_idx..].iter().position(|&c| c == query_char);
+const NONE: i32 = i32::MIN / 2;
 
-        match found {
-            Some(pos) => {
-                indices.push(target_idx + pos);
-                target_idx += pos + 1;
+/// Buffers reused from one candidate to the next, so scoring allocates only
+/// when a longer target than any before comes along.
+#[derive(Debug, Default)]
+pub struct Scratch {
+    query: Vec<char>,
+    target: Vec<char>,
+    /// Where each character of `target` sits in the target as given.
+    origin: Vec<usize>,
+    lower: Vec<char>,
+    first: Vec<usize>,
+    bonus: Vec<i32>,
+    /// score[i * m + j]: best alignment of query[..=i] with query[i] matched at target[j].
+    score: Vec<i32>,
+    /// Bonus carried by the consecutive run ending at (i, j).
+    chunk: Vec<i32>,
+    /// Where query[i - 1] was matched on the best path to (i, j).
+    from: Vec<usize>,
+}
+
+impl Scratch {
+    fn set_query(&mut self, query: &str) {
+        self.query.clear();
+        self.query.extend(fold_chars(query));
+    }
+
+    /// Matches the current query against `target`; positions are only
+    /// collected when `with_indices` is set.
+    fn match_target(&mut self, target: &str, with_indices: bool) -> Option<FuzzyMatch> {
+        normalize_target(target, &mut self.target, &mut self.origin);
+        let (raw, end) = self.align()?;
+
+        let n = self.query.len();
+        let quality = (raw as f64 / max_score(n) as f64).clamp(0.0, 1.0);
+        // Among equally good alignments, shorter targets are closer matches.
+        let coverage = n as f64 / self.target.len() as f64;
+        Some(FuzzyMatch {
+            score: TYPO_CEILING + (1.0 - TYPO_CEILING) * (quality * 0.9 + coverage * 0.1),
+            indices: if with_indices {
+                original_positions(&self.indices(end), &self.origin)
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    /// Best raw alignment score of the query in the target and where it ends.
+    ///
+    /// Characters are compared folded; bonuses are computed from the original
+    /// case so camelCase humps still count.
+    fn align(&mut self) -> Option<(i32, usize)> {
+        let Scratch {
+            query,
+            target,
+            origin: _,
+            lower,
+            first,
+            bonus,
+            score,
+            chunk,
+            from,
+        } = self;
+        let (n, m) = (query.len(), target.len());
+        if n == 0 || n > m {
+            return None;
+        }
+
+        lower.clear();
+        lower.extend(target.iter().map(|&c| fold_char(c)));
+
+        // Cheap rejection, and the earliest position each query character can match.
+        first.clear();
+        let mut pos = 0;
+        for &q in query.iter() {
+            let offset = lower[pos..].iter().position(|&c| c == q)?;
+            first.push(pos + offset);
+            pos += offset + 1;
+        }
+        let start = first[0];
+
+        bonus.clear();
+        let mut prev = CharClass::NonWord;
+        for &c in target.iter() {
+            let class = char_class(c);
+            bonus.push(position_bonus(prev, class));
+            prev = class;
+        }
+
+        for buffer in [&mut *score, &mut *chunk] {
+            buffer.clear();
+            buffer.resize(n * m, NONE);
+        }
+        from.clear();
+        from.resize(n * m, usize::MAX);
+
+        for j in start..m {
+            if lower[j] == query[0] {
+                score[j] = SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER;
+                chunk[j] = bonus[j];
             }
-            None => return None,
         }
+
+        for i in 1..n {
+            let (row, prev_row) = (i * m, (i - 1) * m);
+            // Best predecessor separated by a gap, already charged for its length.
+            let mut gap_best = NONE;
+            let mut gap_from = usize::MAX;
+
+            for j in first[i - 1] + 1..m {
+                if j >= 2 {
+                    let extended = gap_best + SCORE_GAP_EXTENSION;
+                    let opened = score[prev_row + j - 2] + SCORE_GAP_START;
+                    if opened >= extended {
+                        gap_best = opened;
+                        gap_from = j - 2;
+                    } else {
+                        gap_best = extended;
+                    }
+                }
+
+                if lower[j] != query[i] {
+                    continue;
+                }
+
+                let diagonal = score[prev_row + j - 1];
+                let run_bonus = bonus[j].max(chunk[prev_row + j - 1]).max(BONUS_CONSECUTIVE);
+
+                let consecutive = diagonal + SCORE_MATCH + run_bonus;
+                let gapped = gap_best + SCORE_MATCH + bonus[j];
+
+                if diagonal > NONE && consecutive >= gapped {
+                    score[row + j] = consecutive;
+                    chunk[row + j] = run_bonus;
+                    from[row + j] = j - 1;
+                } else if gap_best > NONE {
+                    score[row + j] = gapped;
+                    chunk[row + j] = bonus[j];
+                    from[row + j] = gap_from;
+                }
+            }
+        }
+
+        let last = (n - 1) * m;
+        (0..m)
+            .map(|j| (j, score[last + j]))
+            .filter(|&(_, s)| s > NONE)
+            .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))
+            .map(|(end, best)| (best, end))
     }
 
-    Some(indices)
+    /// Matched positions on the best path ending at `end`.
+    fn indices(&self, end: usize) -> Vec<usize> {
+        let (n, m) = (self.query.len(), self.target.len());
+        let mut indices = vec![0; n];
+        let mut j = end;
+        for i in (0..n).rev() {
+            indices[i] = j;
+            j = self.from[i * m + j];
+        }
+        indices
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::default());
+}
+
+/// Highest raw score a query of `len` characters can reach.
+fn max_score(len: usize) -> i32 {
+    let len = len as i32;
+    len * SCORE_MATCH + BONUS_BOUNDARY * BONUS_FIRST_CHAR_MULTIPLIER + (len - 1) * BONUS_BOUNDARY
+}
+
+/// A successful match: its score and the matched character positions in the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: f64,
+    pub indices: Vec<usize>,
+}
+
+/// Matches `query` against `target`, keeping the positions of the matched characters.
+pub fn fuzzy_match_indices(query: &str, target: &str) -> Option<FuzzyMatch> {
+    SCRATCH.with(|scratch| {
+        let mut scratch = scratch.borrow_mut();
+        scratch.set_query(query);
+        scratch.match_target(target, true)
+    })
+}
+
+/// Scores how well `query` fuzzy-matches `target`, from 0.0 (no match) to 1.0.
+pub fn fuzzy_match(query: &str, target: &str) -> f64 {
+    SCRATCH.with(|scratch| {
+        let mut scratch = scratch.borrow_mut();
+        scratch.set_query(query);
+        scratch.match_target(target, false).map_or(0.0, |m| m.score)
+    })
+}
+
+/// Below this many candidates per worker, spawning threads costs more than it saves.
+const MIN_BATCH_PER_THREAD: usize = 2048;
+
+/// A scored candidate in a top-k heap; orders by score, then earlier index first.
+#[derive(Debug, PartialEq)]
+struct Ranked {
+    score: f64,
+    index: usize,
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Scores `subset` of `candidates` (all of them when `None`) across worker
+/// threads. Returns the best `k` matches, best first, and every index that
+/// matched at all.
+fn batch<T: AsRef<str> + Sync>(
+    query: &str,
+    candidates: &[T],
+    subset: Option<&[usize]>,
+    k: usize,
+) -> (Vec<(usize, FuzzyMatch)>, Vec<usize>) {
+    let all: Vec<usize>;
+    let indices = match subset {
+        Some(subset) => subset,
+        None => {
+            all = (0..candidates.len()).collect();
+            &all
+        }
+    };
+    if query.is_empty() || indices.is_empty() || k == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(indices.len().div_ceil(MIN_BATCH_PER_THREAD))
+        .max(1);
+    let chunk_size = indices.len().div_ceil(workers);
+
+    let results: Vec<(BinaryHeap<Reverse<Ranked>>, Vec<usize>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = indices
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut scratch = Scratch::default();
+                    scratch.set_query(query);
+                    let mut heap = BinaryHeap::with_capacity(k + 1);
+                    let mut matched = Vec::new();
+
+                    for &index in chunk {
+                        let Some(m) = scratch.match_target(candidates[index].as_ref(), false)
+                        else {
+                            continue;
+                        };
+                        matched.push(index);
+                        heap.push(Reverse(Ranked {
+                            score: m.score,
+                            index,
+                        }));
+                        if heap.len() > k {
+                            heap.pop();
+                        }
+                    }
+                    (heap, matched)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+
+    let mut survivors = Vec::new();
+    let mut best: Vec<Ranked> = Vec::new();
+    for (heap, matched) in results {
+        survivors.extend(matched);
+        best.extend(heap.into_iter().map(|Reverse(ranked)| ranked));
+    }
+    best.sort_by(|a, b| b.cmp(a));
+    best.truncate(k);
+
+    // Positions are only worth computing for the results that are kept.
+    let mut scratch = Scratch::default();
+    scratch.set_query(query);
+    let top = best
+        .into_iter()
+        .filter_map(|ranked| {
+            scratch
+                .match_target(candidates[ranked.index].as_ref(), true)
+                .map(|m| (ranked.index, m))
+        })
+        .collect();
+    (top, survivors)
 }
 
-pub fn calculate_score(query: &[char], target: &[char], indices: &[usize]) -> f64 {
-    if indices.is_empty() || query.is_empty() {
-        return 0.0;
+/// Scores every candidate against `query` in parallel and returns the best
+/// `k` as `(candidate index, match)`, best first.
+pub fn score_batch<T: AsRef<str> + Sync>(
+    query: &str,
+    candidates: &[T],
+    k: usize,
+) -> Vec<(usize, FuzzyMatch)> {
+    batch(query, candidates, None, k).0
+}
+
+/// Batch scoring that remembers which candidates matched, so a query that
+/// extends the previous one (the user kept typing) only rescans those.
+#[derive(Debug, Default)]
+pub struct IncrementalMatcher {
+    query: String,
+    /// Names the candidate list the survivors index into.
+    list: String,
+    candidates: usize,
+    survivors: Vec<usize>,
+}
+
+impl IncrementalMatcher {
+    /// Like `score_batch`. `list` names the candidate list: survivors of the
+    /// previous call are only reused when it passed the same `list`, so a
+    /// caller must name every differing list differently.
+    pub fn search<T: AsRef<str> + Sync>(
+        &mut self,
+        list: &str,
+        query: &str,
+        candidates: &[T],
+        k: usize,
+    ) -> Vec<(usize, FuzzyMatch)> {
+        // Anything matching the longer query also matches its prefix.
+        let narrowing = !self.query.is_empty()
+            && list == self.list
+            && candidates.len() == self.candidates
+            && query.starts_with(&self.query);
+        let subset = narrowing.then_some(self.survivors.as_slice());
+
+        let (top, survivors) = batch(query, candidates, subset, k);
+        self.query.clear();
+        self.query.push_str(query);
+        self.list.clear();
+        self.list.push_str(list);
+        self.candidates = candidates.len();
+        self.survivors = survivors;
+        top
     }
+}
 
-    let query_len = query.len() as f64;
-    let target_len = target.len() as f64;
+/// Score lost for every edit a typo-tolerant match needs.
+const TYPO_PENALTY: f64 = 0.2;
 
-    let match_ratio = query_len / target_len.max(1.0);
+/// Typo-tolerant matches score below this and subsequence matches at or
+/// above it, so a misspelling never outranks a real match.
+const TYPO_CEILING: f64 = 0.5;
 
-    let first_match_bonus = 1.0 - (indices[0] as f64 / target_len.max(1.0));
+/// Edits tolerated for a query of `len` characters; short queries get none.
+pub fn max_typos(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        6..=9 => 2,
+        _ => 3,
+    }
+}
+
+/// Typo-tolerant fallback for when `fuzzy_match_indices` finds nothing.
+///
+/// Finds the substring of `target` closest to `query` by Damerau-Levenshtein
+/// (optimal string alignment) distance, so `firfeox` still finds `firefox`.
+/// Matches needing more than `max_typos` edits are rejected.
+pub fn typo_match(query: &str, target: &str) -> Option<FuzzyMatch> {
+    let q: Vec<char> = fold_chars(query).collect();
+    let (mut t, mut origin) = (Vec::new(), Vec::new());
+    normalize_target(target, &mut t, &mut origin);
+    t.iter_mut().for_each(|c| *c = fold_char(*c));
+    let (n, m) = (q.len(), t.len());
+    let bound = max_typos(n);
+    if bound == 0 || m == 0 {
+        return None;
+    }
 
-    let mut consecutive_count = 0;
-    for i in 1..indices.len() {
-        if indices[i] == indices[i - 1] + 1 {
-            consecutive_count += 1;
+    // d[i][j]: edits to turn q[..i] into some substring of t ending at j.
+    let width = m + 1;
+    let mut d = vec![0usize; (n + 1) * width];
+    for i in 1..=n {
+        d[i * width] = i;
+        for j in 1..=m {
+            let substitution = d[(i - 1) * width + j - 1] + usize::from(q[i - 1] != t[j - 1]);
+            let mut best = substitution
+                .min(d[(i - 1) * width + j] + 1)
+                .min(d[i * width + j - 1] + 1);
+            if i > 1 && j > 1 && q[i - 1] == t[j - 2] && q[i - 2] == t[j - 1] {
+                best = best.min(d[(i - 2) * width + j - 2] + 1);
+            }
+            d[i * width + j] = best;
         }
     }
-    let consecutiveness = consecutive_count as f64 / query_len.max(1.0);
 
-    let span = if indices.len() > 1 {
-        (indices[indices.len() - 1] - indices[0] + 1) as f64
-    } else {
-        1.0
-    };
-    let compactness = query_len / span;
-
-    let word_boundary_bonus = if indices[0] == 0 {
-        0.8
-    } else if indices[0] > 0 {
-        let prev_char = target[indices[0] - 1];
-        if prev_char == ' ' || prev_char == '-' || prev_char == '_' || prev_char == '/' {
-            0.5
+    let (end, edits) = (1..=m)
+        .map(|j| (j, d[n * width + j]))
+        .min_by_key(|&(j, edits)| (edits, std::cmp::Reverse(j)))?;
+    if edits > bound {
+        return None;
+    }
+
+    // Walk back along the alignment, keeping target positions that matched a
+    // query character.
+    let mut indices = Vec::new();
+    let (mut i, mut j) = (n, end);
+    while i > 0 {
+        let here = d[i * width + j];
+        if j > 0 && q[i - 1] == t[j - 1] && here == d[(i - 1) * width + j - 1] {
+            indices.push(j - 1);
+            (i, j) = (i - 1, j - 1);
+        } else if i > 1
+            && j > 1
+            && q[i - 1] == t[j - 2]
+            && q[i - 2] == t[j - 1]
+            && here == d[(i - 2) * width + j - 2] + 1
+        {
+            indices.extend([j - 1, j - 2]);
+            (i, j) = (i - 2, j - 2);
+        } else if j > 0 && here == d[(i - 1) * width + j - 1] + 1 {
+            (i, j) = (i - 1, j - 1);
+        } else if here == d[(i - 1) * width + j] + 1 {
+            i -= 1;
         } else {
-            0.0
+            j -= 1;
         }
-    } else {
-        0.0
-    };
-
-    let score = match_ratio * 0.3
-        + first_match_bonus * 0.2
-        + consecutiveness * 0.3
-        + compactness * 0.1
-        + word_boundary_bonus * 0.1;
+    }
+    let indices = original_positions(&indices, &origin);
 
-    score
+    let quality = 1.0 - edits as f64 * TYPO_PENALTY;
+    let coverage = n.min(m) as f64 / m as f64;
+    Some(FuzzyMatch {
+        score: TYPO_CEILING * (quality * 0.9 + coverage * 0.1),
+        indices,
+    })
 }
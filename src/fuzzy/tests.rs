@@ -0,0 +1,112 @@
+//! Alignment scoring, typo tolerance, incremental narrowing and how targets
+//! are normalized.
+
+use super::*;
+
+fn names(hits: &[(usize, FuzzyMatch)], candidates: &[&str]) -> Vec<String> {
+    hits.iter()
+        .map(|(i, _)| candidates[*i].to_string())
+        .collect()
+}
+
+#[test]
+fn narrowing_reuses_survivors_of_the_same_list() {
+    let candidates = ["python", "pytest", "ripgrep"];
+    let mut matcher = IncrementalMatcher::default();
+    matcher.search("sync", "py", &candidates, 10);
+
+    let mut hits = names(&matcher.search("sync", "pyt", &candidates, 10), &candidates);
+    hits.sort();
+    assert_eq!(hits, ["pytest", "python"]);
+}
+
+#[test]
+fn another_list_of_the_same_length_is_scanned_afresh() {
+    let first = ["python", "ripgrep"];
+    let second = ["ripgrep", "python"];
+    let mut matcher = IncrementalMatcher::default();
+    matcher.search("first", "py", &first, 10);
+
+    // Index 0 survived before, but is `ripgrep` in this list.
+    let hits = matcher.search("second", "pyt", &second, 10);
+    assert_eq!(names(&hits, &second), ["python"]);
+}
+
+#[test]
+fn decomposed_targets_normalize_like_queries() {
+    let (mut chars, mut origin) = (Vec::new(), Vec::new());
+    // `é` spelled as `e` and a combining acute accent.
+    normalize_target("cafe\u{301}-x", &mut chars, &mut origin);
+    assert_eq!(chars.iter().collect::<String>(), "café-x");
+    assert_eq!(origin, [0, 1, 2, 3, 5, 6]);
+
+    // Positions come back in terms of the name as given.
+    let m = fuzzy_match_indices("éx", "cafe\u{301}-x").unwrap();
+    assert_eq!(m.indices, [3, 6]);
+}
+
+#[test]
+fn alignment_prefers_a_later_boundary_run_over_the_first_occurrences() {
+    // Greedy matching takes `p` at 1 and `y` at 3.
+    let m = fuzzy_match_indices("py", "xpxy-py").unwrap();
+    assert_eq!(m.indices, [5, 6]);
+
+    let m = fuzzy_match_indices("gh", "graphite-github").unwrap();
+    assert_eq!(m.indices, [9, 12]);
+}
+
+#[test]
+fn word_boundaries_camel_humps_and_runs_score_higher() {
+    assert!(fuzzy_match("b", "a-b") > fuzzy_match("b", "axb"));
+    assert!(fuzzy_match("b", "aBc") > fuzzy_match("b", "abc"));
+    assert!(fuzzy_match("ab", "abxx") > fuzzy_match("ab", "axbx"));
+    // A match at the very start counts as a boundary too.
+    assert!(fuzzy_match("r", "rxx") > fuzzy_match("r", "xrx"));
+}
+
+#[test]
+fn characters_out_of_order_do_not_match() {
+    assert_eq!(fuzzy_match_indices("ba", "abc"), None);
+    assert_eq!(fuzzy_match("xyz", "python"), 0.0);
+    assert_eq!(fuzzy_match("pythons", "python"), 0.0);
+    assert_eq!(fuzzy_match("", "python"), 0.0);
+}
+
+#[test]
+fn longer_queries_tolerate_more_typos() {
+    let limits: Vec<usize> = [2, 3, 5, 6, 9, 10, 20].map(max_typos).to_vec();
+    assert_eq!(limits, [0, 1, 1, 2, 2, 3, 3]);
+
+    // One substitution is fine for four characters, two are not.
+    assert!(typo_match("grap", "grep").is_some());
+    assert_eq!(typo_match("xrap", "grep"), None);
+    // Six characters allow two.
+    assert!(typo_match("pxthxn", "python").is_some());
+    assert_eq!(typo_match("pxxhxn", "python"), None);
+}
+
+#[test]
+fn short_queries_get_no_typos() {
+    assert_eq!(typo_match("gx", "go"), None);
+    assert_eq!(typo_match("x", "go"), None);
+}
+
+#[test]
+fn a_transposition_is_one_edit() {
+    // Two edits as insertions and deletions, over the limit for four characters.
+    let m = typo_match("gerp", "grep").unwrap();
+    assert_eq!(m.indices, [0, 1, 2, 3]);
+
+    let one = typo_match("firfeox", "firefox").unwrap();
+    let two = typo_match("firfeoz", "firefox").unwrap();
+    assert!(one.score > two.score);
+}
+
+#[test]
+fn typos_rank_below_any_subsequence_match() {
+    let typo = typo_match("firfeox", "firefox").unwrap();
+    // A weak subsequence match in a long name.
+    let weak = fuzzy_match("fox", "firefox-developer-edition-nightly-bin");
+    assert!(weak > 0.0);
+    assert!(typo.score < weak, "{} >= {}", typo.score, weak);
+}
@@ -0,0 +1,107 @@
+pub mod pacman_log;
+pub mod selections;
+
+use crate::managers::{Operation, OperationKind};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// One operation trx ran, as stored in the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: Operation,
+    pub command: String,
+    pub exit_code: Option<u32>,
+    pub success: bool,
+    pub duration_secs: f64,
+}
+
+impl HistoryEntry {
+    /// Single-line description used for display and filtering.
+    pub fn summary(&self) -> String {
+        let kind = match self.operation.kind {
+            OperationKind::Install => "install",
+            OperationKind::Remove => "remove",
+            OperationKind::Upgrade => "upgrade",
+            OperationKind::Downgrade => "downgrade",
+            OperationKind::CleanCache => "clean-cache",
+        };
+        format!(
+            "{} {} {}",
+            kind,
+            self.operation.provider,
+            self.operation.packages.join(" ")
+        )
+    }
+}
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keeps trx's data files in `dir` rather than the user's own; only the
+/// first call takes effect.
+#[cfg(test)]
+pub fn use_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// `$XDG_DATA_HOME/trx`, falling back to `~/.local/share/trx`.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = DATA_DIR.get() {
+        return dir.clone();
+    }
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("trx");
+    }
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".local/share/trx")
+}
+
+pub fn history_path() -> PathBuf {
+    data_dir().join("history.jsonl")
+}
+
+/// Appends an entry to the history file, one JSON object per line.
+pub fn record(entry: &HistoryEntry) -> std::io::Result<()> {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(entry)?;
+    writeln!(file, "{}", line)
+}
+
+/// Loads the recorded history, newest first. Unreadable lines are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    let Ok(contents) = fs::read_to_string(history_path()) else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<HistoryEntry> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    entries.reverse();
+    entries
+}
+
+/// The operation that reverts `operation`, if there is one.
+pub fn undo_operation(operation: &Operation) -> Option<Operation> {
+    let kind = match operation.kind {
+        OperationKind::Install => OperationKind::Remove,
+        OperationKind::Remove => OperationKind::Install,
+        OperationKind::Upgrade | OperationKind::Downgrade | OperationKind::CleanCache => {
+            return None;
+        }
+    };
+    Some(Operation::new(
+        kind,
+        &operation.provider,
+        operation.packages.clone(),
+    ))
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use std::fs;
+
+#[cfg(test)]
+mod tests;
+
+pub const PACMAN_LOG: &str = "/var/log/pacman.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogAction {
+    Installed,
+    Upgraded,
+    Downgraded,
+    Reinstalled,
+    Removed,
+}
+
+impl LogAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogAction::Installed => "installed",
+            LogAction::Upgraded => "upgraded",
+            LogAction::Downgraded => "downgraded",
+            LogAction::Reinstalled => "reinstalled",
+            LogAction::Removed => "removed",
+        }
+    }
+
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "installed" => LogAction::Installed,
+            "upgraded" => LogAction::Upgraded,
+            "downgraded" => LogAction::Downgraded,
+            "reinstalled" => LogAction::Reinstalled,
+            "removed" => LogAction::Removed,
+            _ => return None,
+        })
+    }
+}
+
+/// A package transaction recorded by pacman itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEvent {
+    pub timestamp: DateTime<FixedOffset>,
+    pub action: LogAction,
+    pub package: String,
+    pub old_version: Option<String>,
+    pub version: String,
+}
+
+impl LogEvent {
+    /// Single-line description used for display and filtering.
+    pub fn summary(&self) -> String {
+        match &self.old_version {
+            Some(old) => format!(
+                "{} {} ({} -> {})",
+                self.action.as_str(),
+                self.package,
+                old,
+                self.version
+            ),
+            None => format!(
+                "{} {} ({})",
+                self.action.as_str(),
+                self.package,
+                self.version
+            ),
+        }
+    }
+}
+
+fn parse_timestamp(raw: &str) -> Option<DateTime<FixedOffset>> {
+    // pacman >= 5.1 writes `2024-01-15T10:23:45+0100`, older logs `2019-01-01 10:00`.
+    if let Ok(ts) = DateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%z") {
+        return Some(ts);
+    }
+    let naive = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M").ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .single()
+        .map(|ts| ts.fixed_offset())
+}
+
+fn parse_line(line: &str) -> Option<LogEvent> {
+    let rest = line.strip_prefix('[')?;
+    let (raw_ts, rest) = rest.split_once("] ")?;
+    let rest = rest.strip_prefix("[ALPM] ")?;
+
+    let (action, rest) = rest.split_once(' ')?;
+    let action = LogAction::parse(action)?;
+    let (package, versions) = rest.split_once(" (")?;
+    let versions = versions.strip_suffix(')')?;
+
+    let (old_version, version) = match versions.split_once(" -> ") {
+        Some((old, new)) => (Some(old.to_string()), new.to_string()),
+        None => (None, versions.to_string()),
+    };
+
+    Some(LogEvent {
+        timestamp: parse_timestamp(raw_ts)?,
+        action,
+        package: package.to_string(),
+        old_version,
+        version,
+    })
+}
+
+/// Parses package transactions from pacman.log contents, newest first.
+pub fn parse_log(contents: &str) -> Vec<LogEvent> {
+    let mut events: Vec<LogEvent> = contents.lines().filter_map(parse_line).collect();
+    events.reverse();
+    events
+}
+
+pub fn load() -> Vec<LogEvent> {
+    match fs::read(PACMAN_LOG) {
+        Ok(bytes) => parse_log(&String::from_utf8_lossy(&bytes)),
+        Err(_) => Vec::new(),
+    }
+}
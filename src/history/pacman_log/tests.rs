@@ -0,0 +1,68 @@
+//! Parses a captured pacman.log in `fixtures/`.
+
+use super::*;
+
+const PACMAN_LOG: &str = include_str!("fixtures/pacman.log");
+
+fn summaries(events: &[LogEvent]) -> Vec<String> {
+    events.iter().map(LogEvent::summary).collect()
+}
+
+#[test]
+fn only_package_transactions_are_kept_newest_first() {
+    let events = parse_log(PACMAN_LOG);
+    // Scriptlet output, hooks, warnings and pacman's own lines are noise.
+    assert_eq!(
+        summaries(&events),
+        [
+            "removed python-six (1.16.0-8)",
+            "reinstalled ripgrep (14.1.0-1)",
+            "downgraded firefox (122.0-1 -> 121.0.1-1)",
+            "upgraded linux (6.6.10.arch1-1 -> 6.7.arch1-1)",
+            "installed vim (8.1.0629-1)",
+        ]
+    );
+}
+
+#[test]
+fn versions_split_into_old_and_new() {
+    let events = parse_log(PACMAN_LOG);
+    let upgrade = events.iter().find(|e| e.package == "linux").unwrap();
+    assert_eq!(upgrade.action, LogAction::Upgraded);
+    assert_eq!(upgrade.old_version.as_deref(), Some("6.6.10.arch1-1"));
+    assert_eq!(upgrade.version, "6.7.arch1-1");
+
+    let removal = events.iter().find(|e| e.package == "python-six").unwrap();
+    assert_eq!(removal.action, LogAction::Removed);
+    assert_eq!(removal.old_version, None);
+    assert_eq!(removal.version, "1.16.0-8");
+}
+
+#[test]
+fn both_timestamp_formats_parse() {
+    let events = parse_log(PACMAN_LOG);
+
+    // pacman >= 5.1 records the offset.
+    let removal = events.iter().find(|e| e.package == "python-six").unwrap();
+    assert_eq!(removal.timestamp.to_rfc3339(), "2024-02-02T09:00:00-05:00");
+
+    // Older logs are in local time without seconds.
+    let install = events.iter().find(|e| e.package == "vim").unwrap();
+    assert_eq!(
+        install.timestamp.naive_local().to_string(),
+        "2019-01-01 10:00:00"
+    );
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    for line in [
+        "",
+        "[2024-01-15T10:23:45+0100] [ALPM] upgraded linux 6.7",
+        "[2024-01-15T10:23:45+0100] [ALPM] exploded linux (6.7)",
+        "[yesterday] [ALPM] installed vim (9.0-1)",
+        "2024-01-15T10:23:45+0100 [ALPM] installed vim (9.0-1)",
+    ] {
+        assert_eq!(parse_line(line), None, "{line:?}");
+    }
+}
@@ -0,0 +1,27 @@
+use crate::history::data_dir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn selections_path() -> PathBuf {
+    data_dir().join("selections.json")
+}
+
+/// How often each package name was put in the basket.
+pub fn load() -> HashMap<String, u32> {
+    fs::read_to_string(selections_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn record(name: &str) -> std::io::Result<()> {
+    let mut counts = load();
+    *counts.entry(name.to_string()).or_default() += 1;
+
+    let path = selections_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(&counts)?)
+}
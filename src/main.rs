@@ -1,52 +1,66 @@
+mod config;
 mod fuzzy;
+mod history;
 mod managers;
+mod query;
+mod ranking;
 mod ui;
 
-use color_eyre::Result;
-use managers::Package;
-use ratatui::crossterm::{
-    cursor::{Hide, Show},
-    execute,
-    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use color_eyre::{Result, eyre::eyre};
+use managers::backend::registry;
+use managers::{Operation, manifest};
 use ratatui::{init, restore};
-use std::io::{self};
-use std::sync::mpsc;
+use std::path::Path;
+use tokio::sync::mpsc;
 use ui::app::App;
+use ui::event::TerminalEvents;
+use ui::pty::PtyJob;
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     color_eyre::install()?;
+    fuzzy::set_ignore_diacritics(config::get().ignore_diacritics);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let import_path = match args.first().map(String::as_str) {
+        Some("export") => return export_manifest(args.get(1)),
+        Some("import") => match args.get(1) {
+            Some(path) => Some(path.clone()),
+            None => return Err(eyre!("usage: trx import <manifest.toml|manifest.json>")),
+        },
+        Some(other) => return Err(eyre!("unknown command: {}", other)),
+        None => None,
+    };
+
     let mut terminal = init();
-    let (result_tx, result_rx): (mpsc::Sender<Vec<Package>>, mpsc::Receiver<Vec<Package>>) =
-        mpsc::channel();
-    let app_result = App::new(result_tx.clone(), result_rx).run(&mut terminal);
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut app = App::new(tx, rx, registry().to_vec());
+    if let Some(path) = import_path {
+        app.open_manifest(&path);
+    }
+    let app_result = app.run(&mut terminal, TerminalEvents).await;
     restore();
     app_result
 }
 
+/// `trx export [path]`: writes the explicitly installed packages to a manifest.
+fn export_manifest(path: Option<&String>) -> Result<()> {
+    let path = path.map_or("trx-packages.toml", String::as_str);
+    let manifest = manifest::current();
+    manifest::save(&manifest, Path::new(path)).map_err(|e| eyre!("{}", e))?;
+
+    for (provider, names) in &manifest.packages {
+        println!("{}: {} packages", provider, names.len());
+    }
+    println!("Wrote {}", path);
+    Ok(())
+}
+
+/// Starts `cmd` in a pseudo-terminal; the app renders its output in the job pane.
 pub fn execute_external_command(
-    terminal: &mut ratatui::DefaultTerminal,
+    operation: Operation,
     cmd: &str,
     args: &[&str],
-) -> Result<()> {
-    terminal::disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    execute!(terminal.backend_mut(), Show)?;
-
-    println!("\nExecuting: {} {}\n", cmd, args.join(" "));
-    let status = std::process::Command::new(cmd).args(args).status();
-    println!(
-        "\nCommand finished: {:?}\nPress Enter to continue...",
-        status
-    );
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-
-    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
-    terminal::enable_raw_mode()?;
-    execute!(terminal.backend_mut(), Hide)?;
-    terminal.clear()?;
-
-    Ok(())
+) -> Result<PtyJob, Box<dyn std::error::Error>> {
+    PtyJob::spawn(operation, cmd, args)
 }
@@ -0,0 +1,126 @@
+use crate::managers::parser;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process::Command;
+
+/// A package from the local pacman database, as reported by `pacman -Qi`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalPackage {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub required_by: Vec<String>,
+    pub optional_for: Vec<String>,
+    pub explicit: bool,
+}
+
+/// What removing a set of packages would do to the rest of the system.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemovalImpact {
+    /// Installed packages that (transitively) require one of the targets.
+    pub breaks: Vec<String>,
+    /// Dependencies that nothing else needs once the targets are gone (`-Rs`).
+    pub orphaned: Vec<String>,
+}
+
+/// Parses the concatenated `pacman -Qi` output for every installed package.
+pub fn parse_local_db(output: &str) -> Vec<LocalPackage> {
+    parser::parse_info_blocks(output)
+        .into_iter()
+        .filter_map(|fields| {
+            let field = |key: &str| {
+                fields
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map_or(&[][..], |(_, values)| values.as_slice())
+            };
+            let first = |key: &str| field(key).first().cloned().unwrap_or_default();
+
+            Some(LocalPackage {
+                name: field("Name").first()?.clone(),
+                version: first("Version"),
+                description: first("Description"),
+                required_by: parser::split_list(field("Required By")),
+                optional_for: parser::split_list(field("Optional For")),
+                explicit: first("Install Reason").starts_with("Explicitly"),
+            })
+        })
+        .collect()
+}
+
+pub fn local_packages() -> Vec<LocalPackage> {
+    let output = Command::new("pacman")
+        .env("LC_ALL", "C")
+        .arg("-Qi")
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            parse_local_db(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether no installed package requires `pkg`, not even optionally.
+fn unneeded(pkg: &LocalPackage) -> bool {
+    pkg.required_by.is_empty() && pkg.optional_for.is_empty()
+}
+
+/// Dependencies nothing requires or optionally uses any more, the
+/// equivalent of `pacman -Qdt`.
+pub fn orphans(db: &[LocalPackage]) -> Vec<&LocalPackage> {
+    db.iter().filter(|p| !p.explicit && unneeded(p)).collect()
+}
+
+/// Explicitly installed packages nothing depends on, not even optionally,
+/// the equivalent of `pacman -Qet`.
+pub fn leaves(db: &[LocalPackage]) -> Vec<&LocalPackage> {
+    db.iter().filter(|p| p.explicit && unneeded(p)).collect()
+}
+
+pub fn removal_impact(db: &[LocalPackage], targets: &HashSet<String>) -> RemovalImpact {
+    let by_name: HashMap<&str, &LocalPackage> = db.iter().map(|p| (p.name.as_str(), p)).collect();
+
+    // Walk "Required By" edges upwards to find everything that would break.
+    let mut breaks = Vec::new();
+    let mut seen: HashSet<&str> = targets.iter().map(String::as_str).collect();
+    let mut queue: VecDeque<&str> = targets.iter().map(String::as_str).collect();
+    while let Some(name) = queue.pop_front() {
+        if let Some(pkg) = by_name.get(name) {
+            for dependent in &pkg.required_by {
+                if seen.insert(dependent.as_str()) {
+                    breaks.push(dependent.clone());
+                    queue.push_back(dependent.as_str());
+                }
+            }
+        }
+    }
+
+    // Repeatedly drop dependencies whose every dependent is already being removed.
+    let mut removed: HashSet<&str> = targets.iter().map(String::as_str).collect();
+    let mut orphaned = Vec::new();
+    loop {
+        let newly: Vec<&str> = db
+            .iter()
+            .filter(|p| {
+                !p.explicit
+                    && !removed.contains(p.name.as_str())
+                    && !p.required_by.is_empty()
+                    && p.required_by.iter().all(|r| removed.contains(r.as_str()))
+            })
+            .map(|p| p.name.as_str())
+            .collect();
+
+        if newly.is_empty() {
+            break;
+        }
+        for name in newly {
+            removed.insert(name);
+            orphaned.push(name.to_string());
+        }
+    }
+
+    breaks.sort();
+    orphaned.sort();
+    RemovalImpact { breaks, orphaned }
+}
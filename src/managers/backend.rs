@@ -0,0 +1,144 @@
+use crate::managers::details::PackageDetails;
+use crate::managers::review::{self, AurReview, Build};
+use crate::managers::{self, Package, PackageId};
+use crate::ui::pty::PtyJob;
+use futures::future::BoxFuture;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+/// A package source trx can search, inspect and install from.
+pub trait Backend: Send + Sync {
+    /// Provider name stored in `Package::provider`.
+    fn name(&self) -> &'static str;
+    fn search(&self, query: &str) -> Vec<Package>;
+    /// Every package this source offers; used for typo-tolerant matching.
+    fn list_all(&self) -> Vec<Package>;
+    /// Packages the user installed explicitly from this source.
+    fn explicit_packages(&self) -> Vec<String>;
+    /// `-Si` details of `id`; dropping the future cancels the lookup.
+    fn details(&self, id: &PackageId) -> BoxFuture<'static, Option<PackageDetails>>;
+    fn install(
+        &self,
+        names: &HashSet<String>,
+    ) -> Result<Option<PtyJob>, Box<dyn std::error::Error>>;
+    /// The build files to show before `name` is built from source; `None`
+    /// for sources installing prebuilt packages.
+    fn review(&self, _name: &str) -> Option<BoxFuture<'static, Result<AurReview, String>>> {
+        None
+    }
+    /// Builds approved packages from the revisions that were reviewed; only
+    /// called for sources that have reviews.
+    fn build(&self, _builds: &[Build]) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        Err(format!("{} doesn't build from source", self.name()).into())
+    }
+}
+
+pub struct Pacman;
+pub struct Aur;
+
+fn query_names(args: &[&str]) -> Vec<String> {
+    match Command::new("pacman").args(args).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Backend for Pacman {
+    fn name(&self) -> &'static str {
+        "pacman"
+    }
+
+    fn search(&self, query: &str) -> Vec<Package> {
+        managers::search_pacman(query)
+    }
+
+    fn list_all(&self) -> Vec<Package> {
+        managers::list_pacman()
+    }
+
+    fn explicit_packages(&self) -> Vec<String> {
+        // Explicitly installed packages found in the sync databases.
+        query_names(&["-Qqen"])
+    }
+
+    fn details(&self, id: &PackageId) -> BoxFuture<'static, Option<PackageDetails>> {
+        let id = id.clone();
+        Box::pin(async move { managers::details_package(&id).await })
+    }
+
+    fn install(
+        &self,
+        names: &HashSet<String>,
+    ) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        managers::pacman_installation(names)
+    }
+}
+
+impl Backend for Aur {
+    fn name(&self) -> &'static str {
+        "aur"
+    }
+
+    fn search(&self, query: &str) -> Vec<Package> {
+        managers::search_aur(query)
+    }
+
+    fn list_all(&self) -> Vec<Package> {
+        managers::list_aur()
+    }
+
+    fn explicit_packages(&self) -> Vec<String> {
+        // Explicitly installed foreign packages, i.e. built from the AUR.
+        query_names(&["-Qqem"])
+    }
+
+    fn details(&self, id: &PackageId) -> BoxFuture<'static, Option<PackageDetails>> {
+        let id = id.clone();
+        Box::pin(async move { managers::details_package(&id).await })
+    }
+
+    fn install(
+        &self,
+        names: &HashSet<String>,
+    ) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        managers::aur_installation(names)
+    }
+
+    fn review(&self, name: &str) -> Option<BoxFuture<'static, Result<AurReview, String>>> {
+        let name = name.to_string();
+        Some(Box::pin(async move { review::fetch_review(&name).await }))
+    }
+
+    fn build(&self, builds: &[Build]) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        managers::aur_build(builds)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Vec<Arc<dyn Backend>> = vec![Arc::new(Pacman), Arc::new(Aur)];
+    static ref INDEX: Mutex<HashMap<&'static str, Arc<Vec<Package>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Every backend trx knows about, in search order.
+pub fn registry() -> &'static [Arc<dyn Backend>] {
+    &REGISTRY
+}
+
+/// Full package list of `backend`, loaded on first use and kept for the session.
+pub fn package_index(backend: &dyn Backend) -> Arc<Vec<Package>> {
+    if let Some(index) = INDEX.lock().unwrap().get(backend.name()) {
+        return Arc::clone(index);
+    }
+
+    let index = Arc::new(backend.list_all());
+    INDEX
+        .lock()
+        .unwrap()
+        .insert(backend.name(), Arc::clone(&index));
+    index
+}
@@ -0,0 +1,341 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(test)]
+mod tests;
+
+pub const PACMAN_CACHE: &str = "/var/cache/pacman/pkg";
+
+const PACKAGE_EXTENSIONS: &[&str] = &[
+    ".pkg.tar.zst",
+    ".pkg.tar.xz",
+    ".pkg.tar.gz",
+    ".pkg.tar.bz2",
+    ".pkg.tar.lz4",
+    ".pkg.tar",
+];
+
+/// A package archive found in a cache directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPackage {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub path: PathBuf,
+    /// The detached `.sig` next to the archive, removed along with it.
+    pub signature: Option<PathBuf>,
+    /// Bytes of the archive and its signature.
+    pub size: u64,
+    /// Which cache the archive lives in: `pacman`, `yay` or `paru`.
+    pub source: &'static str,
+}
+
+/// Every archive of one package in one cache, newest version first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheGroup {
+    pub name: String,
+    /// The cache holding them; each cache keeps its own versions.
+    pub source: &'static str,
+    pub installed: bool,
+    pub versions: Vec<CachedPackage>,
+}
+
+impl CacheGroup {
+    pub fn size(&self) -> u64 {
+        self.versions.iter().map(|p| p.size).sum()
+    }
+}
+
+/// What a cleanup keeps, in the spirit of `paccache -rk N` and `paccache -ruk0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CleanupPolicy {
+    pub keep: usize,
+    pub remove_uninstalled: bool,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        Self {
+            keep: 3,
+            remove_uninstalled: false,
+        }
+    }
+}
+
+/// Splits `name-pkgver-pkgrel-arch.pkg.tar.zst` into name, `pkgver-pkgrel` and arch.
+pub fn parse_cache_filename(file_name: &str) -> Option<(String, String, String)> {
+    let stem = PACKAGE_EXTENSIONS
+        .iter()
+        .find_map(|ext| file_name.strip_suffix(ext))?;
+
+    // Package names may contain dashes, so peel the fixed fields off the right.
+    let (rest, arch) = stem.rsplit_once('-')?;
+    let (rest, pkgrel) = rest.rsplit_once('-')?;
+    let (name, pkgver) = rest.rsplit_once('-')?;
+
+    if name.is_empty() || pkgver.is_empty() || pkgrel.is_empty() {
+        return None;
+    }
+    Some((
+        name.to_string(),
+        format!("{}-{}", pkgver, pkgrel),
+        arch.to_string(),
+    ))
+}
+
+/// Lists every package archive directly inside `dir`, each with its
+/// signature if there is one.
+pub fn scan_dir(dir: &Path, source: &'static str) -> Vec<CachedPackage> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let files: HashMap<String, (PathBuf, u64)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                (entry.path(), size),
+            )
+        })
+        .collect();
+
+    files
+        .iter()
+        .filter_map(|(file_name, (path, size))| {
+            let (name, version, arch) = parse_cache_filename(file_name)?;
+            let signature = files.get(&format!("{}.sig", file_name));
+
+            Some(CachedPackage {
+                name,
+                version,
+                arch,
+                path: path.clone(),
+                signature: signature.map(|(path, _)| path.clone()),
+                size: size + signature.map_or(0, |(_, size)| *size),
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Cached archives of `name` in the pacman cache, newest version first.
+pub fn cached_versions(name: &str) -> Vec<CachedPackage> {
+    let mut versions: Vec<CachedPackage> = scan_dir(Path::new(PACMAN_CACHE), "pacman")
+        .into_iter()
+        .filter(|p| p.name == name)
+        .collect();
+    versions.sort_by(|a, b| vercmp(&b.version, &a.version));
+    versions
+}
+
+/// Build directories of AUR helpers, one subdirectory per package.
+fn aur_cache_roots() -> Vec<(PathBuf, &'static str)> {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".cache"),
+    };
+    vec![
+        (cache_home.join("yay"), "yay"),
+        (cache_home.join("paru/clone"), "paru"),
+    ]
+}
+
+/// Scans the pacman cache and the AUR helper build caches.
+pub fn scan_all() -> Vec<CachedPackage> {
+    let mut archives = scan_dir(Path::new(PACMAN_CACHE), "pacman");
+
+    for (root, source) in aur_cache_roots() {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                archives.extend(scan_dir(&entry.path(), source));
+            }
+        }
+    }
+
+    archives
+}
+
+/// Groups archives by package name and cache, sorted by name, versions
+/// newest first. A package cached by both pacman and an AUR helper gets a
+/// group in each, so keeping N versions keeps N in every cache.
+pub fn group_archives(
+    archives: Vec<CachedPackage>,
+    installed: &HashSet<String>,
+) -> Vec<CacheGroup> {
+    let mut by_name: BTreeMap<(String, &'static str), Vec<CachedPackage>> = BTreeMap::new();
+    for archive in archives {
+        by_name
+            .entry((archive.name.clone(), archive.source))
+            .or_default()
+            .push(archive);
+    }
+
+    by_name
+        .into_iter()
+        .map(|((name, source), mut versions)| {
+            versions.sort_by(|a, b| vercmp(&b.version, &a.version));
+            CacheGroup {
+                installed: installed.contains(&name),
+                name,
+                source,
+                versions,
+            }
+        })
+        .collect()
+}
+
+/// The archives `policy` would delete, with their signatures. Nothing is
+/// touched on disk.
+pub fn plan_cleanup(groups: &[CacheGroup], policy: CleanupPolicy) -> HashSet<PathBuf> {
+    let mut doomed = HashSet::new();
+
+    for group in groups {
+        let keep = if policy.remove_uninstalled && !group.installed {
+            0
+        } else {
+            policy.keep
+        };
+
+        // Count distinct versions so split-arch archives of one version stay together.
+        let mut kept_versions: Vec<&str> = Vec::new();
+        for archive in &group.versions {
+            if !kept_versions.contains(&archive.version.as_str()) {
+                if kept_versions.len() >= keep {
+                    doomed.insert(archive.path.clone());
+                    doomed.extend(archive.signature.clone());
+                    continue;
+                }
+                kept_versions.push(&archive.version);
+            }
+        }
+    }
+
+    doomed
+}
+
+pub fn installed_version(name: &str) -> Option<String> {
+    let output = Command::new("pacman").args(["-Q", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
+/// Splits `epoch:pkgver-pkgrel`; a missing epoch is `0`.
+fn parse_evr(version: &str) -> (&str, &str, Option<&str>) {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (epoch, rest),
+        _ => ("0", version),
+    };
+    match rest.rsplit_once('-') {
+        Some((ver, rel)) => (epoch, ver, Some(rel)),
+        None => (epoch, rest, None),
+    }
+}
+
+/// Compares two version segments the way alpm's `rpmvercmp` does.
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (sep_i, sep_j) = (i, j);
+        while i < a.len() && !a[i].is_ascii_alphanumeric() {
+            i += 1;
+        }
+        while j < b.len() && !b[j].is_ascii_alphanumeric() {
+            j += 1;
+        }
+        if i >= a.len() || j >= b.len() {
+            break;
+        }
+
+        // More separators means a newer version, e.g. 1.0.1 vs 1.0a.
+        if i - sep_i != j - sep_j {
+            return (i - sep_i).cmp(&(j - sep_j));
+        }
+
+        let (start_i, start_j) = (i, j);
+        let numeric = a[i].is_ascii_digit();
+        if numeric {
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+        } else {
+            while i < a.len() && a[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_alphabetic() {
+                j += 1;
+            }
+        }
+
+        let (seg_a, seg_b) = (&a[start_i..i], &b[start_j..j]);
+        // Segments of different types: numeric ones are newer.
+        if seg_b.is_empty() {
+            return if numeric {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let ord = if numeric {
+            let trim = |s: &[u8]| -> usize { s.iter().take_while(|&&c| c == b'0').count() };
+            let (seg_a, seg_b) = (&seg_a[trim(seg_a)..], &seg_b[trim(seg_b)..]);
+            seg_a.len().cmp(&seg_b.len()).then_with(|| seg_a.cmp(seg_b))
+        } else {
+            seg_a.cmp(seg_b)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    let a_done = i >= a.len();
+    let b_done = j >= b.len();
+    if a_done && b_done {
+        Ordering::Equal
+    } else if (a_done && !b[j].is_ascii_alphabetic()) || (!a_done && a[i].is_ascii_alphabetic()) {
+        // 1.0 < 1.0.1, but 1.0alpha < 1.0.
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+/// Orders full package versions like `pacman`'s `vercmp`.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (epoch_a, ver_a, rel_a) = parse_evr(a);
+    let (epoch_b, ver_b, rel_b) = parse_evr(b);
+
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(ver_a, ver_b))
+        .then_with(|| match (rel_a, rel_b) {
+            (Some(rel_a), Some(rel_b)) => rpmvercmp(rel_a, rel_b),
+            _ => Ordering::Equal,
+        })
+}
@@ -0,0 +1,173 @@
+//! Cache scanning and cleanup planning against scratch directories, and
+//! version ordering.
+
+use super::*;
+
+/// A cache directory holding `files`, each with a few bytes in it.
+fn scratch_cache(test: &str, files: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("trx-cache-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for file in files {
+        fs::write(dir.join(file), b"data").unwrap();
+    }
+    dir
+}
+
+#[test]
+fn signatures_belong_to_their_archives() {
+    let dir = scratch_cache(
+        "signatures",
+        &[
+            "ripgrep-14.1.1-1-x86_64.pkg.tar.zst",
+            "ripgrep-14.1.1-1-x86_64.pkg.tar.zst.sig",
+            "ripgrep-14.1.0-1-x86_64.pkg.tar.zst",
+        ],
+    );
+    let mut archives = scan_dir(&dir, "pacman");
+    archives.sort_by(|a, b| a.version.cmp(&b.version));
+
+    assert_eq!(archives.len(), 2);
+    assert_eq!(archives[0].signature, None);
+    assert_eq!(archives[0].size, 4);
+    assert_eq!(
+        archives[1].signature,
+        Some(dir.join("ripgrep-14.1.1-1-x86_64.pkg.tar.zst.sig"))
+    );
+    assert_eq!(archives[1].size, 8);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn plan_cleanup_removes_signatures_with_their_archives() {
+    let dir = scratch_cache(
+        "cleanup",
+        &[
+            "ripgrep-14.1.1-1-x86_64.pkg.tar.zst",
+            "ripgrep-14.1.1-1-x86_64.pkg.tar.zst.sig",
+            "ripgrep-14.1.0-1-x86_64.pkg.tar.zst",
+            "ripgrep-14.1.0-1-x86_64.pkg.tar.zst.sig",
+            "ripgrep-14.0.3-1-x86_64.pkg.tar.zst",
+        ],
+    );
+    let groups = group_archives(scan_dir(&dir, "pacman"), &HashSet::from(["ripgrep".into()]));
+    let policy = CleanupPolicy {
+        keep: 1,
+        remove_uninstalled: false,
+    };
+
+    let doomed = plan_cleanup(&groups, policy);
+    let expected: HashSet<PathBuf> = [
+        "ripgrep-14.1.0-1-x86_64.pkg.tar.zst",
+        "ripgrep-14.1.0-1-x86_64.pkg.tar.zst.sig",
+        "ripgrep-14.0.3-1-x86_64.pkg.tar.zst",
+    ]
+    .iter()
+    .map(|file| dir.join(file))
+    .collect();
+    assert_eq!(doomed, expected);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn each_cache_keeps_its_own_versions() {
+    let pacman = scratch_cache("per-cache-pacman", &["yay-12.4.2-1-x86_64.pkg.tar.zst"]);
+    let helper = scratch_cache(
+        "per-cache-yay",
+        &[
+            "yay-12.4.2-1-x86_64.pkg.tar.zst",
+            "yay-12.4.1-1-x86_64.pkg.tar.zst",
+        ],
+    );
+    let mut archives = scan_dir(&pacman, "pacman");
+    archives.extend(scan_dir(&helper, "yay"));
+    let groups = group_archives(archives, &HashSet::from(["yay".into()]));
+
+    let sources: Vec<(&str, &str, usize)> = groups
+        .iter()
+        .map(|g| (g.name.as_str(), g.source, g.versions.len()))
+        .collect();
+    assert_eq!(sources, [("yay", "pacman", 1), ("yay", "yay", 2)]);
+
+    let policy = CleanupPolicy {
+        keep: 1,
+        remove_uninstalled: false,
+    };
+    assert_eq!(
+        plan_cleanup(&groups, policy),
+        HashSet::from([helper.join("yay-12.4.1-1-x86_64.pkg.tar.zst")])
+    );
+    let _ = fs::remove_dir_all(&pacman);
+    let _ = fs::remove_dir_all(&helper);
+}
+
+/// Asserts `vercmp` orders each `(older, newer)` pair, both ways round.
+fn assert_older(pairs: &[(&str, &str)]) {
+    for (older, newer) in pairs {
+        assert_eq!(vercmp(older, newer), Ordering::Less, "{older} < {newer}");
+        assert_eq!(vercmp(newer, older), Ordering::Greater, "{newer} > {older}");
+    }
+}
+
+fn assert_same(pairs: &[(&str, &str)]) {
+    for (a, b) in pairs {
+        assert_eq!(vercmp(a, b), Ordering::Equal, "{a} == {b}");
+        assert_eq!(vercmp(b, a), Ordering::Equal, "{b} == {a}");
+    }
+}
+
+#[test]
+fn vercmp_orders_plain_versions_and_pkgrels() {
+    assert_older(&[
+        ("1.5.0", "1.5.1"),
+        ("1.5", "1.5.1"),
+        ("1.9", "1.10"),
+        ("1.5.0-1", "1.5.0-2"),
+        ("1.5.0-2", "1.5.1-1"),
+        ("1.0-2", "1.0-10"),
+        ("1.0-1", "1.0-1.1"),
+    ]);
+    // A missing pkgrel compares equal to any.
+    assert_same(&[("1.5.0", "1.5.0"), ("1.5-1", "1.5"), ("1.1-1", "1.1")]);
+}
+
+#[test]
+fn vercmp_puts_the_epoch_first() {
+    assert_older(&[
+        ("2.0", "1:1.0"),
+        ("9.9-9", "1:0.1-1"),
+        ("1:1.0", "1:2.0"),
+        ("1:9.0", "2:1.0"),
+    ]);
+    assert_same(&[("0:1.0-1", "1.0-1")]);
+}
+
+#[test]
+fn vercmp_ranks_alpha_segments_below_numeric_ones() {
+    assert_older(&[
+        ("1.0a", "1.0b"),
+        ("1.0beta2", "1.0rc1"),
+        ("1.0alpha", "1.0"),
+        ("1.a", "1.1"),
+        ("1.5.b", "1.5.1"),
+        // More separators is newer: 1.0.1 is past 1.0a.
+        ("1.0a", "1.0.1"),
+    ]);
+}
+
+#[test]
+fn vercmp_treats_every_separator_alike() {
+    // Unlike dpkg, `~` is an ordinary separator, not a pre-release mark.
+    assert_older(&[("1.0", "1.0~rc1"), ("1.0", "1.0_1")]);
+    assert_same(&[
+        ("1.0~rc1", "1.0.rc1"),
+        ("1.0_1", "1.0.1"),
+        ("1.0+1", "1.0.1"),
+    ]);
+}
+
+#[test]
+fn vercmp_ignores_leading_zeros() {
+    assert_same(&[("1.01", "1.1"), ("1.001-1", "1.1-1"), ("1.0", "1.00")]);
+    assert_older(&[("1.09", "1.010")]);
+}
@@ -0,0 +1,261 @@
+use crate::managers::PackageId;
+use crate::managers::parser::{self, Fields};
+
+/// An optional dependency and what it adds, e.g. `tk: gitk and git gui`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptDepend {
+    pub name: String,
+    pub reason: Option<String>,
+    /// Marked `[installed]` by `pacman -Qi`.
+    pub installed: bool,
+}
+
+impl OptDepend {
+    fn parse(text: &str) -> Self {
+        let (text, installed) = match text.strip_suffix("[installed]") {
+            Some(rest) => (rest.trim_end(), true),
+            None => (text, false),
+        };
+        let (name, reason) = match text.split_once(':') {
+            Some((name, reason)) => (name.trim(), Some(reason.trim().to_string())),
+            None => (text.trim(), None),
+        };
+        Self {
+            name: name.to_string(),
+            reason: reason.filter(|r| !r.is_empty()),
+            installed,
+        }
+    }
+}
+
+/// What `pacman -Si`/`-Qi` or `yay -Si` prints about one package.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageDetails {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub provides: Vec<String>,
+    pub depends: Vec<String>,
+    pub optdepends: Vec<OptDepend>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+    pub download_size: Option<String>,
+    pub installed_size: Option<String>,
+    pub packager: Option<String>,
+    pub build_date: Option<String>,
+    /// How the package was verified, e.g. `SHA-256 Sum` and `Signature`.
+    pub validated_by: Vec<String>,
+    /// Every field not modelled above, such as `Repository`, `Votes` or
+    /// `Install Reason`, in the order printed; multi-line values are joined
+    /// with newlines.
+    pub extra: Vec<(String, String)>,
+}
+
+/// A titled group of `(label, lines)` fields for the details pane.
+pub struct DetailSection {
+    pub title: &'static str,
+    pub fields: Vec<(String, Vec<String>)>,
+}
+
+/// Repositories with package pages on archlinux.org.
+const OFFICIAL_REPOS: &[&str] = &[
+    "core",
+    "extra",
+    "multilib",
+    "core-testing",
+    "extra-testing",
+    "multilib-testing",
+];
+
+/// Fields that `PackageDetails` keeps typed rather than in `extra`.
+const MODELLED: &[&str] = &[
+    "Name",
+    "Version",
+    "Description",
+    "URL",
+    "Licenses",
+    "Groups",
+    "Provides",
+    "Depends On",
+    "Optional Deps",
+    "Conflicts With",
+    "Replaces",
+    "Download Size",
+    "Installed Size",
+    "Packager",
+    "Build Date",
+    "Validated By",
+];
+
+impl PackageDetails {
+    /// Builds the model from parsed `Key : value` fields; `None` without a name.
+    pub fn from_fields(fields: &Fields) -> Option<Self> {
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&[][..], |(_, values)| values.as_slice())
+        };
+        // Single values; pacman prints `None` for an empty one.
+        let single = |key: &str| {
+            let value = field(key).join(" ");
+            (!value.is_empty() && value != "None").then_some(value)
+        };
+        let list = |key: &str| parser::split_list(field(key));
+
+        let optdepends = field("Optional Deps")
+            .iter()
+            .flat_map(|line| {
+                // yay prints names only, several to a line.
+                if line.contains(':') {
+                    vec![OptDepend::parse(line)]
+                } else {
+                    parser::split_list(std::slice::from_ref(line))
+                        .iter()
+                        .map(|name| OptDepend::parse(name))
+                        .collect()
+                }
+            })
+            .collect();
+
+        let extra = fields
+            .iter()
+            .filter(|(key, _)| !MODELLED.contains(&key.as_str()))
+            .map(|(key, values)| (key.clone(), values.join("\n")))
+            .collect();
+
+        Some(Self {
+            name: single("Name")?,
+            version: single("Version").unwrap_or_default(),
+            description: single("Description").unwrap_or_default(),
+            url: single("URL"),
+            licenses: list("Licenses"),
+            groups: list("Groups"),
+            provides: list("Provides"),
+            depends: list("Depends On"),
+            optdepends,
+            conflicts: list("Conflicts With"),
+            replaces: list("Replaces"),
+            download_size: single("Download Size"),
+            installed_size: single("Installed Size"),
+            packager: single("Packager"),
+            build_date: single("Build Date"),
+            // Entries are several words each, separated by two spaces.
+            validated_by: field("Validated By")
+                .iter()
+                .flat_map(|line| line.split("  "))
+                .map(str::trim)
+                .filter(|v| !v.is_empty() && *v != "None")
+                .map(str::to_string)
+                .collect(),
+            extra,
+        })
+    }
+
+    /// A field from `extra`, e.g. `AUR URL`.
+    pub fn extra(&self, key: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The package's page on the AUR or archlinux.org, for the official
+    /// repositories only; `id` says where these details came from.
+    pub fn package_page(&self, id: &PackageId) -> Option<String> {
+        match id.provider.as_str() {
+            "aur" => Some(self.extra("AUR URL").map_or_else(
+                || format!("https://aur.archlinux.org/packages/{}", self.name),
+                str::to_string,
+            )),
+            "pacman" if OFFICIAL_REPOS.contains(&id.repo.as_str()) => {
+                let arch = self.extra("Architecture")?;
+                Some(format!(
+                    "https://archlinux.org/packages/{}/{}/{}/",
+                    id.repo, arch, self.name
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// The details grouped for display: what the package is, how it relates
+    /// to others, how it was built, then everything else. Empty fields and
+    /// sections are left out.
+    pub fn sections(&self) -> Vec<DetailSection> {
+        fn add(fields: &mut Vec<(String, Vec<String>)>, label: &str, lines: Vec<String>) {
+            if !lines.is_empty() {
+                fields.push((label.to_string(), lines));
+            }
+        }
+        let one = |value: &Option<String>| value.iter().cloned().collect::<Vec<_>>();
+        let joined = |values: &[String]| {
+            if values.is_empty() {
+                Vec::new()
+            } else {
+                vec![values.join(" ")]
+            }
+        };
+
+        let mut about = Vec::new();
+        if !self.description.is_empty() {
+            add(&mut about, "Description", vec![self.description.clone()]);
+        }
+        add(&mut about, "URL", one(&self.url));
+        add(&mut about, "Licenses", joined(&self.licenses));
+        add(&mut about, "Groups", joined(&self.groups));
+
+        let mut relations = Vec::new();
+        add(&mut relations, "Depends On", joined(&self.depends));
+        add(
+            &mut relations,
+            "Optional Deps",
+            self.optdepends
+                .iter()
+                .map(|opt| {
+                    let mut line = opt.name.clone();
+                    if let Some(reason) = &opt.reason {
+                        line.push_str(": ");
+                        line.push_str(reason);
+                    }
+                    if opt.installed {
+                        line.push_str(" [installed]");
+                    }
+                    line
+                })
+                .collect(),
+        );
+        add(&mut relations, "Provides", joined(&self.provides));
+        add(&mut relations, "Conflicts With", joined(&self.conflicts));
+        add(&mut relations, "Replaces", joined(&self.replaces));
+
+        let mut build = Vec::new();
+        add(&mut build, "Download Size", one(&self.download_size));
+        add(&mut build, "Installed Size", one(&self.installed_size));
+        add(&mut build, "Packager", one(&self.packager));
+        add(&mut build, "Build Date", one(&self.build_date));
+        add(&mut build, "Validated By", joined(&self.validated_by));
+
+        let mut other = Vec::new();
+        for (key, value) in &self.extra {
+            if value.is_empty() || value == "None" {
+                continue;
+            }
+            add(&mut other, key, value.lines().map(str::to_string).collect());
+        }
+
+        [
+            ("Package", about),
+            ("Dependencies", relations),
+            ("Build", build),
+            ("Other", other),
+        ]
+        .into_iter()
+        .filter(|(_, fields)| !fields.is_empty())
+        .map(|(title, fields)| DetailSection { title, fields })
+        .collect()
+    }
+}
@@ -0,0 +1,70 @@
+use crate::managers::{Package, PackageId, parser};
+
+/// Runs pacman with `args`, returning its output or the first line of what
+/// it reported as the error. The command is killed if the future is dropped.
+async fn pacman(args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new("pacman")
+        // Messages are matched in English.
+        .env("LC_ALL", "C")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run pacman: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(stderr
+            .lines()
+            .next()
+            .unwrap_or("pacman failed")
+            .trim_start_matches("error: ")
+            .to_string())
+    }
+}
+
+/// Every path `id` installs, directories ending in `/`: from the local
+/// database when `installed`, otherwise from the sync file databases.
+pub async fn file_list(id: &PackageId, installed: bool) -> Result<Vec<String>, String> {
+    let output = if installed || id.provider == "local" {
+        pacman(&["-Ql", &id.name]).await?
+    } else if id.provider == "pacman" {
+        let qualified = id.to_string();
+        let output = pacman(&["-Fl", &qualified]).await?;
+        if output.is_empty() {
+            return Err("No file list; run `pacman -Fy` to download the file databases".into());
+        }
+        output
+    } else {
+        return Err(format!(
+            "File lists of {} packages are known once installed",
+            id.provider
+        ));
+    };
+    Ok(parser::parse_file_list(&output))
+}
+
+/// Packages owning `target`, a path or a command name: installed owners
+/// from the local database first, then packages in the sync repositories
+/// shipping a file of that name or path.
+pub async fn find_owners(target: &str) -> Vec<Package> {
+    // `-Qo` looks up bare names in `$PATH`, so commands resolve as well.
+    // `--` keeps typed text starting with `-` from being read as options.
+    let mut owners = match pacman(&["-Qo", "--", target]).await {
+        Ok(output) => parser::parse_query_owners(&output),
+        Err(_) => Vec::new(),
+    };
+
+    // `-F` exits non-zero when nothing matches.
+    if let Ok(output) = pacman(&["-F", "--", target]).await {
+        let installed: Vec<String> = owners.iter().map(|o| o.id.name.clone()).collect();
+        owners.extend(
+            parser::parse_file_owners(&output)
+                .into_iter()
+                .filter(|pkg| !installed.contains(&pkg.id.name)),
+        );
+    }
+    owners
+}
@@ -0,0 +1,108 @@
+use crate::managers::backend::registry;
+use crate::managers::installed_names;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+/// A declarative package list: explicitly installed packages per provider.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub packages: BTreeMap<String, Vec<String>>,
+}
+
+/// How a manifest differs from the packages on this system.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManifestDiff {
+    /// `(provider, name)` pairs listed in the manifest but not installed.
+    pub missing: Vec<(String, String)>,
+    /// `(provider, name)` pairs installed explicitly but absent from the manifest.
+    pub extra: Vec<(String, String)>,
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Captures the explicitly installed packages of every registered backend.
+pub fn current() -> Manifest {
+    let mut packages = BTreeMap::new();
+    for backend in registry() {
+        let mut names = backend.explicit_packages();
+        names.sort();
+        packages.insert(backend.name().to_string(), names);
+    }
+    Manifest { packages }
+}
+
+/// Writes `manifest` as JSON for `*.json` paths and TOML otherwise.
+pub fn save(manifest: &Manifest, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = if is_json(path) {
+        serde_json::to_string_pretty(manifest)?
+    } else {
+        toml::to_string_pretty(manifest)?
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Whether `name` is something pacman would accept as a package name.
+/// Names end up in `sudo pacman -S` and in AUR clone paths, so anything
+/// that reads as an option or a path is refused.
+fn is_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '.'])
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"@._+-".contains(&b))
+}
+
+/// Reads a manifest written by `save`, refusing names that aren't package names.
+pub fn load(path: &Path) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    let manifest: Manifest = if is_json(path) {
+        serde_json::from_str(&contents)?
+    } else {
+        toml::from_str(&contents)?
+    };
+
+    for (provider, names) in &manifest.packages {
+        if let Some(name) = names.iter().find(|name| !is_package_name(name)) {
+            return Err(format!("{:?} under {} is not a package name", name, provider).into());
+        }
+    }
+    Ok(manifest)
+}
+
+/// Compares `wanted` against `explicit` packages and everything `installed`.
+pub fn diff(wanted: &Manifest, explicit: &Manifest, installed: &HashSet<String>) -> ManifestDiff {
+    let mut result = ManifestDiff::default();
+
+    for (provider, names) in &wanted.packages {
+        for name in names {
+            if !installed.contains(name) {
+                result.missing.push((provider.clone(), name.clone()));
+            }
+        }
+    }
+
+    let listed: HashSet<&String> = wanted.packages.values().flatten().collect();
+    for (provider, names) in &explicit.packages {
+        for name in names {
+            if !listed.contains(name) {
+                result.extra.push((provider.clone(), name.clone()));
+            }
+        }
+    }
+
+    result
+}
+
+/// Diffs `wanted` against the running system.
+pub fn diff_system(wanted: &Manifest) -> ManifestDiff {
+    diff(wanted, &current(), &installed_names())
+}
@@ -0,0 +1,88 @@
+//! Manifest loading and diffing against scratch files.
+
+use super::*;
+use std::path::PathBuf;
+
+/// A manifest file named `name` holding `contents`.
+fn scratch_manifest(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("trx-manifest-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn manifest(entries: &[(&str, &[&str])]) -> Manifest {
+    Manifest {
+        packages: entries
+            .iter()
+            .map(|(provider, names)| {
+                let names = names.iter().map(|n| n.to_string()).collect();
+                (provider.to_string(), names)
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn loads_toml_and_json_alike() {
+    let toml = scratch_manifest(
+        "packages.toml",
+        "[packages]\npacman = [\"ripgrep\", \"gtk+3\"]\naur = [\"yay-bin\"]\n",
+    );
+    let json = scratch_manifest(
+        "packages.json",
+        r#"{"packages": {"pacman": ["ripgrep", "gtk+3"], "aur": ["yay-bin"]}}"#,
+    );
+    let expected = manifest(&[("aur", &["yay-bin"]), ("pacman", &["ripgrep", "gtk+3"])]);
+
+    assert_eq!(load(&toml).unwrap(), expected);
+    assert_eq!(load(&json).unwrap(), expected);
+}
+
+#[test]
+fn saved_manifests_load_back() {
+    let path = scratch_manifest("roundtrip.toml", "");
+    let saved = manifest(&[("pacman", &["lib32-glibc", "python-pytest"])]);
+    save(&saved, &path).unwrap();
+    assert_eq!(load(&path).unwrap(), saved);
+}
+
+#[test]
+fn names_that_read_as_options_or_paths_are_refused() {
+    for name in [
+        "--config=/tmp/x.conf",
+        "-Syu",
+        "../../../home/u/project",
+        "/etc/passwd",
+        ".hidden",
+        "foo/bar",
+        "Firefox",
+        "fire fox",
+        "",
+    ] {
+        let path = scratch_manifest(
+            "refused.json",
+            &format!(r#"{{"packages": {{"pacman": ["ripgrep", {:?}]}}}}"#, name),
+        );
+        assert!(load(&path).is_err(), "{name:?} was accepted");
+    }
+}
+
+#[test]
+fn diff_lists_missing_and_extra_packages() {
+    let wanted = manifest(&[("pacman", &["ripgrep", "fd"]), ("aur", &["yay-bin"])]);
+    let explicit = manifest(&[("pacman", &["ripgrep", "htop"]), ("aur", &[])]);
+    // Installed as a dependency still counts as present.
+    let installed = HashSet::from(["ripgrep", "htop", "fd"].map(String::from));
+
+    let result = diff(&wanted, &explicit, &installed);
+    assert_eq!(
+        result.missing,
+        vec![("aur".to_string(), "yay-bin".to_string())]
+    );
+    assert_eq!(
+        result.extra,
+        vec![("pacman".to_string(), "htop".to_string())]
+    );
+}
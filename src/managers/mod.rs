@@ -1,61 +1,184 @@
+pub mod analysis;
+pub mod backend;
+pub mod cache;
+pub mod details;
+pub mod files;
+pub mod manifest;
+pub mod parser;
+pub mod review;
+
+use crate::config::Config;
 use crate::execute_external_command;
-use ratatui::DefaultTerminal;
+use crate::managers::details::PackageDetails;
+use crate::ranking::Rank;
+use crate::ui::pty::PtyJob;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Package {
+/// Identifies one package build from one source, e.g. `extra/foo 1.0-1` via pacman.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageId {
+    /// Backend that installs it: `pacman`, `aur` or `local`.
     pub provider: String,
+    /// Repository it is listed in, e.g. `extra`, `aur` or `local`; empty if unknown.
+    pub repo: String,
     pub name: String,
     pub version: String,
+}
+
+impl PackageId {
+    pub fn new(provider: &str, repo: &str, name: &str, version: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            repo: repo.to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    /// Parses `repo/name` as printed by `pacman -Ss`; a bare name gets `provider` as repo.
+    pub fn parse(provider: &str, qualified: &str, version: &str) -> Self {
+        let (repo, name) = qualified.split_once('/').unwrap_or((provider, qualified));
+        Self::new(provider, repo, name, version)
+    }
+}
+
+impl fmt::Display for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.repo.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}/{}", self.repo, self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub id: PackageId,
     pub description: String,
     pub score: f64,
+    /// Character positions in `id.name` matched by the query.
+    pub matched: Vec<usize>,
+    pub installed: bool,
+    /// AUR votes and popularity, as printed by `yay -Ss`.
+    pub votes: Option<u32>,
+    pub popularity: Option<f64>,
+    /// Package groups, e.g. `base-devel`, as printed by `pacman -Ss`.
+    pub groups: Vec<String>,
+    /// How `score` was put together, once the result has been ranked.
+    pub rank: Option<Rank>,
 }
 
-lazy_static::lazy_static! {
-    static ref DETAILS_CACHE: Arc<Mutex<HashMap<String, HashMap<String, String>>>> =
-        Arc::new(Mutex::new(HashMap::new()));
+impl Package {
+    pub fn new(id: PackageId, description: &str) -> Self {
+        Self {
+            id,
+            description: description.to_string(),
+            score: 0.0,
+            matched: Vec::new(),
+            installed: false,
+            votes: None,
+            popularity: None,
+            groups: Vec::new(),
+            rank: None,
+        }
+    }
 }
 
-fn parse_alternating_lines(lines: &[&str], manager: String, query: &str) -> Vec<Package> {
-    let mut res = Vec::new();
-    let mut i = 0;
+/// Collapses results offering the same package name into one group per name.
+///
+/// Each group lists its sources by `config` priority, then score, so the first
+/// entry is the preferred one. Groups are ordered by their best score.
+pub fn merge_sources(packages: Vec<Package>, config: &Config) -> Vec<Vec<Package>> {
+    let mut seen = HashSet::new();
+    let mut groups: Vec<Vec<Package>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for pkg in packages {
+        if !seen.insert(pkg.id.clone()) {
+            continue;
+        }
+        match index.get(&pkg.id.name) {
+            Some(&i) => groups[i].push(pkg),
+            None => {
+                index.insert(pkg.id.name.clone(), groups.len());
+                groups.push(vec![pkg]);
+            }
+        }
+    }
 
-    while i + 1 < lines.len() {
-        let first_line = lines[i];
-        let second_line = lines[i + 1];
+    let by_score = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+    let best = |group: &[Package]| group.iter().map(|p| p.score).fold(0.0, f64::max);
 
-        let parts: Vec<&str> = first_line.split_whitespace().collect();
+    for group in &mut groups {
+        group.sort_by(|a, b| {
+            config
+                .provider_rank(&a.id.provider)
+                .cmp(&config.provider_rank(&b.id.provider))
+                .then_with(|| by_score(a.score, b.score))
+        });
+    }
+    groups.sort_by(|a, b| by_score(best(a), best(b)));
+    groups
+}
 
-        if parts.len() >= 2 {
-            let package = parts[0].to_string();
-            let version = parts[1].to_string();
-            let description = second_line.trim().to_string();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    Install,
+    Remove,
+    Upgrade,
+    /// Installs archives from the package cache; `packages` holds their paths.
+    Downgrade,
+    /// Deletes archives from a package cache; `packages` holds their paths.
+    CleanCache,
+}
 
-            let package_name = package.split('/').last().unwrap_or(&package).to_string();
+/// A package operation trx runs in the job pane and records in its history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub kind: OperationKind,
+    pub provider: String,
+    pub packages: Vec<String>,
+}
 
-            let score = crate::fuzzy::fuzzy_match(query, &package_name);
-            res.push(Package {
-                provider: manager.clone(),
-                name: package,
-                version,
-                description,
-                score,
-            });
+impl Operation {
+    pub fn new(kind: OperationKind, provider: &str, packages: Vec<String>) -> Self {
+        Self {
+            kind,
+            provider: provider.to_string(),
+            packages,
         }
-
-        i += 2;
     }
+}
 
-    res.retain(|p| p.score > 0.01);
-    res.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+lazy_static::lazy_static! {
+    static ref DETAILS_CACHE: Arc<Mutex<HashMap<PackageId, PackageDetails>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
 
-    res
+/// Every package in the sync databases (`pacman -Sl`), without descriptions.
+pub fn list_pacman() -> Vec<Package> {
+    match Command::new("pacman").arg("-Sl").output() {
+        Ok(output) if output.status.success() => {
+            parser::parse_sync_list(&String::from_utf8_lossy(&output.stdout), "pacman")
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Every package in the AUR (`yay -Sl aur`), without descriptions.
+pub fn list_aur() -> Vec<Package> {
+    match Command::new("yay").args(["-Sl", "aur"]).output() {
+        Ok(output) if output.status.success() => {
+            parser::parse_sync_list(&String::from_utf8_lossy(&output.stdout), "aur")
+        }
+        _ => Vec::new(),
+    }
 }
 
 pub fn search_pacman(search_word: &str) -> Vec<Package> {
@@ -63,14 +186,15 @@ pub fn search_pacman(search_word: &str) -> Vec<Package> {
         return Vec::new();
     }
 
-    let output = Command::new("pacman").args(&["-Ss", search_word]).output();
+    // Each word is a separate regex; pacman lists packages matching all of them.
+    let output = Command::new("pacman")
+        .args(["-Ss", "--"])
+        .args(search_word.split_whitespace())
+        .output();
 
     match output {
         Ok(output) if output.status.success() => {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            let lines: Vec<&str> = output_str.lines().collect();
-            let manager = "pacman".to_string();
-            parse_alternating_lines(&lines, manager, search_word)
+            parser::parse_search(&String::from_utf8_lossy(&output.stdout), "pacman")
         }
         _ => Vec::new(),
     }
@@ -81,104 +205,226 @@ pub fn search_aur(search_word: &str) -> Vec<Package> {
         return Vec::new();
     }
 
-    let output = Command::new("yay").args(&["-Ss", search_word]).output();
+    let output = Command::new("yay")
+        .args(["-Ss", "--"])
+        .args(search_word.split_whitespace())
+        .output();
 
     match output {
         Ok(output) if output.status.success() => {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            let lines: Vec<&str> = output_str.lines().collect();
-
-            let manager = "aur".to_string();
-            parse_alternating_lines(&lines, manager, search_word)
+            parser::parse_search(&String::from_utf8_lossy(&output.stdout), "aur")
         }
         _ => Vec::new(),
     }
 }
 
-pub fn details_package(package: &str, provider: &str) -> Option<HashMap<String, String>> {
-    {
-        let cache = DETAILS_CACHE.lock().unwrap();
-        if let Some(cached) = cache.get(package) {
-            return Some(cached.clone());
-        }
+/// Names of every installed package, explicit or not.
+pub fn installed_names() -> HashSet<String> {
+    match Command::new("pacman").arg("-Qq").output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => HashSet::new(),
     }
-    let pure_name = package.split('/').last().unwrap_or(package);
-    let output = if provider == "pacman".to_string() {
-        Command::new("pacman")
-            .args(&["-Si", pure_name])
-            .output()
-            .ok()?
-    } else if provider == "aur" {
-        Command::new("yay")
-            .args(&["-Si", pure_name])
-            .output()
-            .ok()?
-    } else {
-        return None;
+}
+
+/// Details already fetched for `id` this session.
+pub fn cached_details(id: &PackageId) -> Option<PackageDetails> {
+    DETAILS_CACHE.lock().unwrap().get(id).cloned()
+}
+
+/// `-Si`/`-Qi` details of `id`, fetched once and cached for the session.
+///
+/// The command is killed if the future is dropped, so aborting the task
+/// awaiting it cancels a lookup that is no longer needed.
+pub async fn details_package(id: &PackageId) -> Option<PackageDetails> {
+    if let Some(cached) = cached_details(id) {
+        return Some(cached);
+    }
+    // `repo/name` picks the right source when several repos carry the package.
+    let qualified = id.to_string();
+    let (program, args) = match id.provider.as_str() {
+        "pacman" => ("pacman", ["-Si", qualified.as_str()]),
+        "aur" => ("yay", ["-Si", qualified.as_str()]),
+        "local" => ("pacman", ["-Qi", id.name.as_str()]),
+        _ => return None,
     };
+    let output = tokio::process::Command::new(program)
+        // Field names are matched in English.
+        .env("LC_ALL", "C")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
 
     if !output.status.success() {
         return None;
     }
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let mut info = HashMap::new();
-
-    for line in output_str.lines() {
-        if let Some((key, value)) = line.split_once(" : ") {
-            info.insert(key.trim().to_string(), value.trim().to_string());
-        }
-    }
+    let details = parser::parse_details(&String::from_utf8_lossy(&output.stdout))?;
+    DETAILS_CACHE
+        .lock()
+        .unwrap()
+        .insert(id.clone(), details.clone());
+    Some(details)
+}
 
-    if info.is_empty() {
-        None
-    } else {
-        // Cache the result
-        let mut cache = DETAILS_CACHE.lock().unwrap();
-        cache.insert(package.to_string(), info.clone());
-        Some(info)
-    }
+fn pure_names(selected_names: &HashSet<String>) -> Vec<String> {
+    let mut names: Vec<String> = selected_names
+        .iter()
+        .map(|name| name.split('/').next_back().unwrap_or(name).to_string())
+        .collect();
+    names.sort();
+    names
 }
 
 pub fn pacman_installation(
-    terminal: &mut DefaultTerminal,
     selected_names: &HashSet<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
     if selected_names.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
-    let pure_names: Vec<String> = selected_names
-        .iter()
-        .map(|name| name.split('/').last().unwrap_or(name).to_string())
-        .collect();
+    let operation = Operation::new(OperationKind::Install, "pacman", pure_names(selected_names));
 
-    let mut args: Vec<String> = vec!["pacman".to_string(), "-S".to_string()];
-    args.extend(pure_names);
+    let mut args: Vec<String> = vec!["pacman".to_string(), "-S".to_string(), "--".to_string()];
+    args.extend(operation.packages.iter().cloned());
 
     let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-    execute_external_command(terminal, "sudo", &args_ref)?;
-
-    Ok(())
+    Ok(Some(execute_external_command(
+        operation, "sudo", &args_ref,
+    )?))
 }
 pub fn aur_installation(
-    terminal: &mut DefaultTerminal,
     selected_names: &HashSet<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
     if selected_names.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
-    let pure_names: Vec<String> = selected_names
-        .iter()
-        .map(|name| name.split('/').last().unwrap_or(name).to_string())
-        .collect();
+    let operation = Operation::new(OperationKind::Install, "aur", pure_names(selected_names));
+
+    // yay escalates with sudo itself, inside the job pane.
+    let mut args: Vec<String> = vec!["-S".to_string()];
+    args.extend(operation.packages.iter().cloned());
+
+    let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    Ok(Some(execute_external_command(operation, "yay", &args_ref)?))
+}
+
+/// Builds reviewed AUR packages in order from their clones, refusing any
+/// whose build files moved away from the revision that was approved.
+pub fn aur_build(builds: &[review::Build]) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+    if builds.is_empty() {
+        return Ok(None);
+    }
+
+    let names = builds.iter().map(|b| b.name.clone()).collect();
+    let operation = Operation::new(OperationKind::Install, "aur", names);
+
+    // Takes `dir revision flags` triples; makepkg escalates with sudo itself.
+    let script = r#"set -e
+while [ "$#" -gt 0 ]; do
+    cd "$1"
+    if [ "$(git rev-parse HEAD)" != "$2" ] || ! git diff --quiet HEAD; then
+        echo "${1##*/} changed since it was reviewed; review it again" >&2
+        exit 1
+    fi
+    makepkg --syncdeps --install --clean --force $3
+    shift 3
+done"#;
+    let mut args: Vec<String> = vec!["-c".to_string(), script.to_string(), "sh".to_string()];
+    for build in builds {
+        args.push(review::clone_dir(&build.base).to_string_lossy().to_string());
+        args.push(build.revision.clone());
+        args.push(if build.dependency { "--asdeps" } else { "" }.to_string());
+    }
+
+    let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    Ok(Some(execute_external_command(operation, "sh", &args_ref)?))
+}
 
-    let mut args: Vec<String> = vec!["yay".to_string(), "-S".to_string()];
-    args.extend(pure_names);
+pub fn pacman_removal(
+    provider: &str,
+    selected_names: &HashSet<String>,
+) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+    if selected_names.is_empty() {
+        return Ok(None);
+    }
+
+    let operation = Operation::new(OperationKind::Remove, provider, pure_names(selected_names));
+
+    let mut args: Vec<String> = vec!["pacman".to_string(), "-Rns".to_string(), "--".to_string()];
+    args.extend(operation.packages.iter().cloned());
 
     let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-    execute_external_command(terminal, "sudo", &args_ref)?;
+    Ok(Some(execute_external_command(
+        operation, "sudo", &args_ref,
+    )?))
+}
 
-    Ok(())
+/// Upgrades the repository packages; AUR updates go through a review and
+/// `aur_build` instead.
+pub fn system_upgrade() -> Result<PtyJob, Box<dyn std::error::Error>> {
+    let operation = Operation::new(OperationKind::Upgrade, "pacman", Vec::new());
+    execute_external_command(operation, "sudo", &["pacman", "-Syu"])
+}
+
+/// Installs specific package archives, e.g. an older version from the cache.
+pub fn cache_installation(
+    archives: &[String],
+) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+    if archives.is_empty() {
+        return Ok(None);
+    }
+
+    let operation = Operation::new(OperationKind::Downgrade, "pacman", archives.to_vec());
+
+    let mut args: Vec<&str> = vec!["pacman", "-U"];
+    args.extend(archives.iter().map(|s| s.as_str()));
+
+    Ok(Some(execute_external_command(operation, "sudo", &args)?))
+}
+
+/// Deletes cached package archives; the pacman cache is root-owned, so it needs sudo.
+pub fn cache_cleanup(
+    provider: &str,
+    archives: &[String],
+) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+    if archives.is_empty() {
+        return Ok(None);
+    }
+
+    let operation = Operation::new(OperationKind::CleanCache, provider, archives.to_vec());
+
+    let mut args: Vec<&str> = vec!["rm", "-f", "--"];
+    args.extend(archives.iter().map(|s| s.as_str()));
+
+    let job = if provider == "pacman" {
+        execute_external_command(operation, "sudo", &args)?
+    } else {
+        execute_external_command(operation, args[0], &args[1..])?
+    };
+    Ok(Some(job))
+}
+
+/// Starts `operation` again, e.g. when replaying it from the history view.
+pub fn run_operation(operation: &Operation) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+    let names: HashSet<String> = operation.packages.iter().cloned().collect();
+    match (operation.kind, operation.provider.as_str()) {
+        // Packages removed from the local database come back from the repositories.
+        (OperationKind::Install, "pacman" | "local") => pacman_installation(&names),
+        (OperationKind::Install, provider) => {
+            Err(format!("{} packages are built after a review", provider).into())
+        }
+        (OperationKind::Remove, provider) => pacman_removal(provider, &names),
+        (OperationKind::Upgrade, "pacman") => system_upgrade().map(Some),
+        (OperationKind::Upgrade, provider) => {
+            Err(format!("{} upgrades are built after a review", provider).into())
+        }
+        (OperationKind::Downgrade, _) => cache_installation(&operation.packages),
+        (OperationKind::CleanCache, provider) => cache_cleanup(provider, &operation.packages),
+    }
 }
@@ -0,0 +1,406 @@
+//! Parsers for the text pacman and yay print.
+//!
+//! Every format trx reads lives here so it can be tested against captured
+//! output in `fixtures/`: search results (`-Ss`), sync listings (`-Sl`),
+//! package information (`-Si`/`-Qi`), file lists and owners, AUR updates
+//! (`yay -Qua`), and the `.SRCINFO` and comments of AUR packages.
+
+use crate::managers::details::PackageDetails;
+use crate::managers::review::AurComment;
+use crate::managers::{Package, PackageId};
+
+#[cfg(test)]
+mod tests;
+
+/// `Key : value` fields of one `-Si`/`-Qi` block, in the order printed.
+///
+/// Each field keeps one entry per output line, so lists pacman prints one
+/// item per line, like `Optional Deps`, come out whole.
+pub type Fields = Vec<(String, Vec<String>)>;
+
+/// Splits `-Si`/`-Qi` output into one `Fields` per package.
+pub fn parse_info_blocks(output: &str) -> Vec<Fields> {
+    let mut blocks = Vec::new();
+    let mut fields: Fields = Vec::new();
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                blocks.push(std::mem::take(&mut fields));
+            }
+            continue;
+        }
+
+        // Values continued on the next line are indented past the colon.
+        if line.starts_with(char::is_whitespace) {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(" : ").or_else(|| {
+            // A field with an empty value has nothing after the colon.
+            line.strip_suffix(" :").map(|key| (key, ""))
+        }) {
+            let value = value.trim();
+            let values = if value.is_empty() {
+                Vec::new()
+            } else {
+                vec![value.to_string()]
+            };
+            fields.push((key.trim().to_string(), values));
+        }
+    }
+
+    if !fields.is_empty() {
+        blocks.push(fields);
+    }
+    blocks
+}
+
+/// The fields of the first package in `-Si`/`-Qi` output; `-Si` prints one
+/// block per repository carrying the name.
+pub fn parse_info(output: &str) -> Fields {
+    parse_info_blocks(output)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+}
+
+/// The first package in `-Si`/`-Qi` output as a typed model.
+pub fn parse_details(output: &str) -> Option<PackageDetails> {
+    PackageDetails::from_fields(&parse_info(output))
+}
+
+/// Words of a list field such as `Depends On`, whether printed on one line
+/// or across several; `None` means an empty list.
+pub fn split_list(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .flat_map(|line| line.split_whitespace())
+        .filter(|word| *word != "None")
+        .map(str::to_string)
+        .collect()
+}
+
+/// A `(...)` or `[...]` annotation after the version in `-Ss` output.
+#[derive(Debug, PartialEq)]
+enum Annotation<'a> {
+    Paren(&'a str),
+    Bracket(&'a str),
+}
+
+/// Splits `(base-devel) [installed: 1.2-1]` into its annotations; text
+/// outside brackets is ignored.
+fn annotations(text: &str) -> Vec<Annotation<'_>> {
+    let mut found = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['(', '[']) {
+        let close = if rest[start..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(len) = rest[start + 1..].find(close) else {
+            break;
+        };
+        let inner = rest[start + 1..start + 1 + len].trim();
+        found.push(if close == ')' {
+            Annotation::Paren(inner)
+        } else {
+            Annotation::Bracket(inner)
+        });
+        rest = &rest[start + 1 + len + 1..];
+    }
+    found
+}
+
+/// Whether `text` is yay's `1.2 MiB 5.0 MiB` download and installed sizes.
+fn is_sizes(text: &str) -> bool {
+    text.split_whitespace().all(|word| {
+        word.parse::<f64>().is_ok() || matches!(word, "B" | "KiB" | "MiB" | "GiB" | "TiB")
+    })
+}
+
+/// Parses one `repo/name version [annotations]` line of `-Ss` output.
+fn parse_search_header(line: &str, provider: &str) -> Option<Package> {
+    let (qualified, rest) = line.trim_end().split_once(' ')?;
+    let rest = rest.trim_start();
+    let (version, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+
+    let mut id = PackageId::parse(provider, qualified, version);
+    // yay lists sync repo packages too; those belong to pacman.
+    if provider == "aur" && id.repo != "aur" {
+        id.provider = "pacman".to_string();
+    }
+    let mut pkg = Package::new(id, "");
+
+    for annotation in annotations(rest) {
+        match annotation {
+            // pacman: `[installed]`, or `[installed: 1.2-1]` when another version is.
+            Annotation::Bracket(text) | Annotation::Paren(text)
+                if text.to_lowercase().starts_with("installed") =>
+            {
+                pkg.installed = true;
+            }
+            // yay: `(+votes popularity)` on AUR packages.
+            Annotation::Paren(text) if text.starts_with('+') => {
+                let mut words = text[1..].split_whitespace();
+                pkg.votes = words.next().and_then(|v| v.parse().ok());
+                pkg.popularity = words.next().and_then(|p| p.parse().ok());
+            }
+            Annotation::Paren(text)
+                if is_sizes(text)
+                    || text.starts_with("Out-of-date")
+                    || text.starts_with("Orphaned") => {}
+            // pacman: the groups the package belongs to, e.g. `(base-devel)`.
+            Annotation::Paren(text) => {
+                pkg.groups
+                    .extend(text.split_whitespace().map(str::to_string));
+            }
+            Annotation::Bracket(_) => {}
+        }
+    }
+
+    Some(pkg)
+}
+
+/// Parses `-Ss` output from pacman or yay: a `repo/name version` header per
+/// package followed by its indented description.
+///
+/// Results are unscored; the search query ranks and filters them afterwards.
+pub fn parse_search(output: &str, provider: &str) -> Vec<Package> {
+    let mut res: Vec<Package> = Vec::new();
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(pkg) = res.last_mut() {
+                if !pkg.description.is_empty() {
+                    pkg.description.push(' ');
+                }
+                pkg.description.push_str(line.trim());
+            }
+        } else if let Some(pkg) = parse_search_header(line, provider) {
+            res.push(pkg);
+        }
+    }
+
+    res
+}
+
+/// Parses `repo name version [installed]` lines printed by `pacman -Sl`.
+pub fn parse_sync_list(output: &str, provider: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, ' ');
+            let (repo, name, version) = (parts.next()?, parts.next()?, parts.next()?);
+            let mut pkg = Package::new(PackageId::new(provider, repo, name, version), "");
+            pkg.installed = annotations(parts.next().unwrap_or(""))
+                .iter()
+                .any(|a| matches!(a, Annotation::Bracket(text) if text.starts_with("installed")));
+            Some(pkg)
+        })
+        .collect()
+}
+
+/// Paths in `pacman -Ql` or `pacman -Fl` output, each starting with `/`;
+/// directories end in `/`.
+pub fn parse_file_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        // `name path`; paths may contain spaces, package names never do.
+        .filter_map(|line| line.split_once(' '))
+        .map(|(_, path)| {
+            // `-Fl` prints paths relative to the root.
+            if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("/{}", path)
+            }
+        })
+        .collect()
+}
+
+/// Parses `pacman -Qo` output into the installed packages owning the
+/// queried paths, each described by the path it owns.
+pub fn parse_query_owners(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (path, owner) = line.split_once(" is owned by ")?;
+            let (name, version) = owner.trim().split_once(' ')?;
+            let mut pkg = Package::new(PackageId::new("local", "local", name, version), path);
+            pkg.installed = true;
+            Some(pkg)
+        })
+        .collect()
+}
+
+/// Parses `pacman -F` output into the repository packages containing the
+/// queried file, each described by the matching paths.
+///
+/// A file name prints a `repo/name version` header per package followed by
+/// the indented paths; a full path prints `path is owned by repo/name version`.
+pub fn parse_file_owners(output: &str) -> Vec<Package> {
+    let mut res = Vec::new();
+    for line in output.lines() {
+        if let Some((path, owner)) = line.split_once(" is owned by ") {
+            let (qualified, version) = owner.trim().split_once(' ').unwrap_or((owner, ""));
+            let (version, installed) = match version.split_once(' ') {
+                Some((version, rest)) => (version, rest.contains("[installed]")),
+                None => (version, false),
+            };
+            let mut pkg = Package::new(
+                PackageId::parse("pacman", qualified, version),
+                &format!("/{}", path.trim_start_matches('/')),
+            );
+            pkg.installed = installed;
+            res.push(pkg);
+        } else if line.starts_with(char::is_whitespace) {
+            if let Some(pkg) = res.last_mut() {
+                let path = format!("/{}", line.trim().trim_start_matches('/'));
+                if !pkg.description.is_empty() {
+                    pkg.description.push(' ');
+                }
+                pkg.description.push_str(&path);
+            }
+        } else if let Some(pkg) = parse_search_header(line, "pacman") {
+            res.push(pkg);
+        }
+    }
+    res
+}
+
+/// Decodes the entities the AUR uses in comment text.
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Text of an HTML fragment: tags dropped, paragraphs and line breaks kept
+/// as line breaks, blank runs collapsed.
+fn html_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        // Line breaks in the source are plain whitespace in HTML.
+        text.push_str(&rest[..start].replace('\n', " "));
+        let Some(len) = rest[start..].find('>') else {
+            // A tag cut off at the end of the fragment.
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + len].trim_start_matches('/');
+        if tag.starts_with("br") || tag.starts_with('p') || tag.starts_with("li") {
+            text.push('\n');
+        }
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(&rest.replace('\n', " "));
+
+    let lines: Vec<String> = decode_entities(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    let mut out: Vec<&str> = Vec::new();
+    for line in &lines {
+        if !line.is_empty() || out.last().is_some_and(|last| !last.is_empty()) {
+            out.push(line);
+        }
+    }
+    out.join("\n").trim().to_string()
+}
+
+/// Parses the comments on an AUR package page, newest first as shown.
+pub fn parse_aur_comments(page: &str) -> Vec<AurComment> {
+    let mut comments = Vec::new();
+    let mut rest = page;
+
+    while let Some(start) = rest.find("class=\"comment-header\"") {
+        rest = &rest[start..];
+        let header_end = rest.find("</h4>").unwrap_or(rest.len());
+        // `<form>`s in the header hold the edit and delete buttons.
+        let header = rest[..header_end].split("<form").next().unwrap_or_default();
+        let header = html_text(header.split_once('>').map_or("", |(_, h)| h));
+        let (author, date) = header
+            .split_once(" commented on ")
+            .unwrap_or((header.as_str(), ""));
+
+        rest = &rest[header_end..];
+        let body_end = rest.find("class=\"comment-header\"").unwrap_or(rest.len());
+        let body = &rest[..body_end];
+        let text = body
+            .find("class=\"article-content\"")
+            .map(|i| {
+                let content = &body[i..];
+                // Stop before the next comment's `<h4` or the page footer.
+                let content = content.split("<h4").next().unwrap_or_default();
+                let content = content.split("id=\"pkgfoot\"").next().unwrap_or_default();
+                html_text(content.split_once('>').map_or("", |(_, c)| c))
+            })
+            .unwrap_or_default();
+
+        comments.push(AurComment {
+            author: author.trim().to_string(),
+            date: date.trim().to_string(),
+            text,
+        });
+        rest = &rest[body_end..];
+    }
+    comments
+}
+
+/// Names of the packages `yay -Qua` lists as out of date, one
+/// `name old -> new` line each.
+pub fn parse_updates(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains(" -> "))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Everything a `.SRCINFO` needs installed to build and check its
+/// packages on this machine's architecture, version constraints kept.
+/// Split packages depending on each other are built together and left out.
+pub fn parse_srcinfo_depends(srcinfo: &str) -> Vec<String> {
+    let arch = std::env::consts::ARCH;
+    let mut names = Vec::new();
+    let mut depends: Vec<&str> = Vec::new();
+
+    for line in srcinfo.lines() {
+        let Some((key, value)) = line.trim().split_once(" = ") else {
+            continue;
+        };
+        let kind = key.strip_suffix(arch).and_then(|k| k.strip_suffix('_'));
+        match kind.unwrap_or(key) {
+            "pkgname" => names.push(value),
+            "depends" | "makedepends" | "checkdepends" if !depends.contains(&value) => {
+                depends.push(value)
+            }
+            _ => {}
+        }
+    }
+    depends
+        .into_iter()
+        .filter(|depend| !names.contains(&dependency_name(depend)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The package name in a dependency such as `python>=3.11`.
+pub fn dependency_name(depend: &str) -> &str {
+    depend.split(['<', '>', '=']).next().unwrap_or(depend)
+}
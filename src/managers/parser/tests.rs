@@ -0,0 +1,409 @@
+//! Parses output captured from pacman and yay in `fixtures/`.
+
+use super::*;
+use crate::managers::analysis::{leaves, orphans, parse_local_db};
+use crate::managers::details::OptDepend;
+
+const PACMAN_SS: &str = include_str!("fixtures/pacman_ss.txt");
+const YAY_SS: &str = include_str!("fixtures/yay_ss.txt");
+const PACMAN_SL: &str = include_str!("fixtures/pacman_sl.txt");
+const PACMAN_SI: &str = include_str!("fixtures/pacman_si.txt");
+const YAY_SI: &str = include_str!("fixtures/yay_si.txt");
+const PACMAN_QI: &str = include_str!("fixtures/pacman_qi.txt");
+
+fn field<'a>(fields: &'a Fields, key: &str) -> &'a [String] {
+    fields
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, values)| values.as_slice())
+        .unwrap_or_else(|| panic!("no {key} field"))
+}
+
+fn named<'a>(packages: &'a [Package], name: &str) -> &'a Package {
+    packages
+        .iter()
+        .find(|p| p.id.name == name)
+        .unwrap_or_else(|| panic!("{name} not parsed"))
+}
+
+#[test]
+fn pacman_search_reads_every_package() {
+    let packages = parse_search(PACMAN_SS, "pacman");
+    let names: Vec<&str> = packages.iter().map(|p| p.id.name.as_str()).collect();
+    assert_eq!(names, ["ripgrep", "ripgrep-all", "make", "kate", "ugrep"]);
+
+    let ripgrep = named(&packages, "ripgrep");
+    assert_eq!(
+        ripgrep.id,
+        PackageId::new("pacman", "extra", "ripgrep", "14.1.1-1")
+    );
+    assert_eq!(
+        ripgrep.description,
+        "A search tool that combines the usability of ag with the raw speed of grep"
+    );
+    assert!(ripgrep.installed);
+    assert!(!named(&packages, "ugrep").installed);
+}
+
+#[test]
+fn pacman_search_reads_installed_versions_and_groups() {
+    let packages = parse_search(PACMAN_SS, "pacman");
+
+    // Another version is installed: still installed, and the listed version wins.
+    let rga = named(&packages, "ripgrep-all");
+    assert!(rga.installed);
+    assert_eq!(rga.id.version, "0.10.6-2");
+    assert!(rga.groups.is_empty());
+
+    let make = named(&packages, "make");
+    assert_eq!(make.groups, ["base-devel"]);
+    assert!(make.installed);
+
+    let kate = named(&packages, "kate");
+    assert_eq!(kate.groups, ["kde-applications", "kde-utilities"]);
+    assert!(!kate.installed);
+}
+
+#[test]
+fn yay_search_reads_votes_and_ignores_status_annotations() {
+    let packages = parse_search(YAY_SS, "aur");
+    assert_eq!(packages.len(), 4);
+
+    let git = named(&packages, "ripgrep-git");
+    assert_eq!(git.id.provider, "aur");
+    assert_eq!(git.votes, Some(12));
+    assert_eq!(git.popularity, Some(0.35));
+    assert!(!git.installed);
+    assert!(git.groups.is_empty());
+
+    let yay = named(&packages, "yay");
+    assert_eq!(yay.votes, Some(2300));
+    assert!(yay.installed);
+
+    let orphan = named(&packages, "rga-fzf");
+    assert_eq!(orphan.votes, Some(0));
+    assert!(orphan.groups.is_empty());
+    assert_eq!(orphan.description, "fzf frontend for ripgrep-all");
+}
+
+#[test]
+fn yay_search_hands_repo_packages_to_pacman() {
+    let packages = parse_search(YAY_SS, "aur");
+    let ripgrep = named(&packages, "ripgrep");
+
+    assert_eq!(
+        ripgrep.id,
+        PackageId::new("pacman", "extra", "ripgrep", "14.1.1-1")
+    );
+    assert!(ripgrep.installed);
+    // `(1.6 MiB 4.7 MiB)` is sizes, not groups.
+    assert!(ripgrep.groups.is_empty());
+    assert_eq!(ripgrep.votes, None);
+}
+
+#[test]
+fn sync_list_reads_installed_flags() {
+    let packages = parse_sync_list(PACMAN_SL, "pacman");
+    let summary: Vec<(&str, &str, bool)> = packages
+        .iter()
+        .map(|p| (p.id.repo.as_str(), p.id.name.as_str(), p.installed))
+        .collect();
+
+    assert_eq!(
+        summary,
+        [
+            ("core", "bash", true),
+            ("core", "make", true),
+            ("extra", "ripgrep", false),
+            ("multilib", "lib32-glibc", false),
+        ]
+    );
+    assert_eq!(packages[3].id.version, "2.40+r16+gaa533d58ff-2");
+}
+
+#[test]
+fn pacman_info_keeps_every_optional_dependency() {
+    let fields = parse_info(PACMAN_SI);
+
+    assert_eq!(
+        field(&fields, "Optional Deps"),
+        [
+            "git-zsh-completion: upstream zsh completion",
+            "tk: gitk and git gui",
+            "openssh: ssh transport and crypto",
+            "perl-libwww: git svn",
+        ]
+    );
+    // The field after the list is not swallowed by it.
+    assert_eq!(field(&fields, "Conflicts With"), ["None"]);
+}
+
+#[test]
+fn pacman_info_reads_the_first_repository_only() {
+    let fields = parse_info(PACMAN_SI);
+    assert_eq!(field(&fields, "Repository"), ["extra"]);
+    assert_eq!(field(&fields, "Version"), ["2.47.0-1"]);
+    assert_eq!(parse_info_blocks(PACMAN_SI).len(), 2);
+}
+
+#[test]
+fn pacman_info_keeps_field_order() {
+    let fields = parse_info(PACMAN_SI);
+    let keys: Vec<&str> = fields.iter().take(4).map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, ["Repository", "Name", "Version", "Description"]);
+    assert_eq!(
+        field(&fields, "Packager"),
+        ["Christian Hesse <eworm@archlinux.org>"]
+    );
+}
+
+#[test]
+fn yay_info_reads_aur_fields() {
+    let fields = parse_info(YAY_SI);
+    assert_eq!(
+        field(&fields, "AUR URL"),
+        ["https://aur.archlinux.org/packages/yay"]
+    );
+    assert_eq!(field(&fields, "Votes"), ["2300"]);
+    assert_eq!(
+        split_list(field(&fields, "Depends On")),
+        ["pacman>6.1", "git"]
+    );
+    assert_eq!(
+        split_list(field(&fields, "Check Deps")),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        split_list(field(&fields, "Optional Deps")),
+        ["sudo", "doas"]
+    );
+}
+
+#[test]
+fn local_db_joins_wrapped_lists() {
+    let db = parse_local_db(PACMAN_QI);
+    assert_eq!(db.len(), 3);
+
+    let glibc = &db[0];
+    assert_eq!(glibc.name, "glibc");
+    assert_eq!(
+        glibc.required_by,
+        ["bash", "coreutils", "gcc-libs", "grep", "make"]
+    );
+    assert!(glibc.optional_for.is_empty());
+    assert!(!glibc.explicit);
+
+    let ripgrep = &db[1];
+    assert!(ripgrep.required_by.is_empty());
+    assert!(ripgrep.explicit);
+}
+
+#[test]
+fn optional_dependencies_are_neither_orphans_nor_leaves() {
+    let db = parse_local_db(PACMAN_QI);
+    let perl = &db[2];
+    assert!(perl.required_by.is_empty());
+    assert_eq!(perl.optional_for, ["glibc"]);
+
+    // Like `pacman -Qdt`, which keeps what another package optionally uses.
+    assert!(orphans(&db).is_empty());
+    let leaves: Vec<&str> = leaves(&db).iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(leaves, ["ripgrep"]);
+}
+
+#[test]
+fn annotations_split_on_brackets() {
+    assert_eq!(
+        annotations("(base-devel) [installed: 1.2-1] trailing"),
+        [
+            Annotation::Paren("base-devel"),
+            Annotation::Bracket("installed: 1.2-1"),
+        ]
+    );
+    assert_eq!(annotations("(unterminated"), []);
+}
+
+#[test]
+fn details_model_pacman_info() {
+    let details = parse_details(PACMAN_SI).expect("git parses");
+    assert_eq!(details.name, "git");
+    assert_eq!(details.version, "2.47.0-1");
+    assert_eq!(details.url.as_deref(), Some("https://git-scm.com/"));
+    assert_eq!(details.licenses, ["GPL-2.0-only"]);
+    assert!(details.groups.is_empty());
+    assert_eq!(details.depends.len(), 7);
+    assert_eq!(details.optdepends.len(), 4);
+    assert_eq!(
+        details.optdepends[1],
+        OptDepend {
+            name: "tk".to_string(),
+            reason: Some("gitk and git gui".to_string()),
+            installed: false,
+        }
+    );
+    assert_eq!(details.installed_size.as_deref(), Some("37.39 MiB"));
+    assert_eq!(
+        details.validated_by,
+        ["MD5 Sum", "SHA-256 Sum", "Signature"]
+    );
+    assert_eq!(
+        details.extra,
+        [
+            ("Repository".to_string(), "extra".to_string()),
+            ("Architecture".to_string(), "x86_64".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn details_model_reads_installed_optdepends_and_aur_names() {
+    let glibc = parse_details(PACMAN_QI).expect("glibc parses");
+    assert!(glibc.optdepends[1].installed);
+    assert_eq!(glibc.optdepends[1].reason.as_deref(), Some("for mtrace"));
+    assert!(glibc.download_size.is_none());
+
+    let yay = parse_details(YAY_SI).expect("yay parses");
+    let names: Vec<&str> = yay.optdepends.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, ["sudo", "doas"]);
+    assert!(yay.optdepends.iter().all(|o| o.reason.is_none()));
+    assert!(yay.extra.iter().any(|(k, v)| k == "Votes" && v == "2300"));
+}
+
+#[test]
+fn details_sections_skip_empty_fields() {
+    let details = parse_details(PACMAN_SI).unwrap();
+    let sections = details.sections();
+    let titles: Vec<&str> = sections.iter().map(|s| s.title).collect();
+    assert_eq!(titles, ["Package", "Dependencies", "Build", "Other"]);
+
+    let labels: Vec<&str> = sections[1].fields.iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(labels, ["Depends On", "Optional Deps"]);
+    assert_eq!(sections[1].fields[1].1.len(), 4);
+}
+
+const PACMAN_QL: &str = include_str!("fixtures/pacman_ql.txt");
+const PACMAN_FL: &str = include_str!("fixtures/pacman_fl.txt");
+const PACMAN_QO: &str = include_str!("fixtures/pacman_qo.txt");
+const PACMAN_F: &str = include_str!("fixtures/pacman_f.txt");
+const PACMAN_F_PATH: &str = include_str!("fixtures/pacman_f_path.txt");
+
+#[test]
+fn file_lists_are_absolute_with_directories_marked() {
+    let installed = parse_file_list(PACMAN_QL);
+    assert_eq!(installed.len(), 14);
+    assert_eq!(installed[0], "/usr/");
+    assert_eq!(installed[2], "/usr/bin/rg");
+
+    // `-Fl` prints paths relative to the root.
+    let repo = parse_file_list(PACMAN_FL);
+    assert_eq!(repo[2], "/usr/bin/git");
+    assert_eq!(
+        repo.last().map(String::as_str),
+        Some("/usr/share/git/my file with spaces.txt")
+    );
+}
+
+#[test]
+fn query_owners_are_installed_packages() {
+    let owners = parse_query_owners(PACMAN_QO);
+    assert_eq!(owners.len(), 2);
+    assert_eq!(
+        owners[0].id,
+        PackageId::new("local", "local", "ripgrep", "14.1.1-1")
+    );
+    assert_eq!(owners[0].description, "/usr/bin/rg");
+    assert!(owners.iter().all(|p| p.installed));
+}
+
+#[test]
+fn file_owners_list_matching_paths() {
+    let owners = parse_file_owners(PACMAN_F);
+    let names: Vec<&str> = owners.iter().map(|p| p.id.name.as_str()).collect();
+    assert_eq!(names, ["git", "git-lfs", "lib32-git-shim"]);
+
+    let git = &owners[0];
+    assert_eq!(git.id, PackageId::new("pacman", "extra", "git", "2.47.0-1"));
+    assert!(git.installed);
+    assert_eq!(
+        git.description,
+        "/usr/bin/git /usr/share/bash-completion/completions/git"
+    );
+    assert!(!owners[1].installed);
+    assert_eq!(owners[2].id.repo, "multilib");
+}
+
+#[test]
+fn file_owners_of_a_full_path() {
+    let owners = parse_file_owners(PACMAN_F_PATH);
+    assert_eq!(owners.len(), 1);
+    assert_eq!(
+        owners[0].id,
+        PackageId::new("pacman", "extra", "ripgrep", "14.1.1-1")
+    );
+    assert_eq!(owners[0].description, "/usr/bin/rg");
+}
+
+const AUR_COMMENTS: &str = include_str!("fixtures/aur_comments.html");
+
+#[test]
+fn aur_comments_keep_author_date_and_paragraphs() {
+    let comments = parse_aur_comments(AUR_COMMENTS);
+    assert_eq!(comments.len(), 2);
+
+    assert_eq!(comments[0].author, "jguer");
+    assert_eq!(comments[0].date, "2024-10-19 12:34 (UTC)");
+    assert_eq!(
+        comments[0].text,
+        "12.4.2 fixes the \"--devel\" regression.\n\
+         Please run yay -Syu && yay -Sc if builds fail.\n\
+         \n\
+         Thanks for the reports!"
+    );
+
+    assert_eq!(comments[1].author, "someone");
+    assert!(
+        comments[1]
+            .date
+            .starts_with("2024-10-18 08:01 (UTC) (edited on")
+    );
+    // Nothing from the page footer.
+    assert_eq!(
+        comments[1].text,
+        "Build fails with go 1.23: <undefined: slices.Concat> 'ok'"
+    );
+}
+
+const SRCINFO: &str = include_str!("fixtures/srcinfo.txt");
+const YAY_QUA: &str = include_str!("fixtures/yay_qua.txt");
+
+#[test]
+fn srcinfo_depends_cover_every_split_package_for_this_arch() {
+    let depends = parse_srcinfo_depends(SRCINFO);
+    let mut expected = vec![
+        "python-pytest",
+        "python-build",
+        "python-installer",
+        "python>=3.11",
+        "poppler-cpp",
+    ];
+    match std::env::consts::ARCH {
+        "x86_64" => expected.push("libpdfium-bin"),
+        "aarch64" => expected.push("libpdfium-arm"),
+        _ => {}
+    }
+    // `python-pdfgrep-docs` needs `python-pdfgrep`, built alongside it.
+    assert_eq!(depends, expected);
+
+    assert_eq!(dependency_name("python>=3.11"), "python");
+    assert_eq!(dependency_name("python-pdfgrep=2.2.0"), "python-pdfgrep");
+    assert_eq!(dependency_name("poppler-cpp"), "poppler-cpp");
+}
+
+#[test]
+fn aur_updates_list_the_outdated_names() {
+    let updates = parse_updates(YAY_QUA);
+    assert_eq!(
+        updates,
+        ["google-chrome", "paru-bin", "visual-studio-code-bin"]
+    );
+}
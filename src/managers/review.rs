@@ -0,0 +1,310 @@
+use crate::history::data_dir;
+use crate::managers::parser;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A comment left on the package's AUR page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AurComment {
+    pub author: String,
+    /// As the AUR prints it, e.g. `2024-10-19 12:34 (UTC)`.
+    pub date: String,
+    pub text: String,
+}
+
+/// How the build files changed since the user last reviewed the package.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Changes {
+    /// Never reviewed before.
+    New,
+    Unchanged,
+    /// `git diff` output from the reviewed revision to the current one.
+    Diff(String),
+    /// The reviewed revision is gone from the AUR history, e.g. after a
+    /// force push.
+    Unknown(String),
+}
+
+/// Everything shown before an AUR package is built.
+#[derive(Debug, Clone)]
+pub struct AurReview {
+    pub name: String,
+    /// The AUR git repository, shared by split packages.
+    pub base: String,
+    /// Commit of the build files being reviewed.
+    pub revision: String,
+    /// `PKGBUILD` first, then the `.install` scripts it names.
+    pub files: Vec<(String, String)>,
+    pub changes: Changes,
+    pub comments: Result<Vec<AurComment>, String>,
+    /// Dependencies only the AUR has, reviewed and built before this one.
+    pub depends: Vec<String>,
+}
+
+/// An approved package base, built from the revision that was reviewed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Build {
+    pub name: String,
+    pub base: String,
+    pub revision: String,
+    /// Pulled in for another build rather than picked, so installed `--asdeps`.
+    pub dependency: bool,
+}
+
+/// Where the AUR repositories are cloned: `$XDG_CACHE_HOME/trx/aur`,
+/// falling back to `~/.cache/trx/aur`.
+fn clone_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("trx/aur");
+    }
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".cache/trx/aur")
+}
+
+/// The clone of `base` that is reviewed and then built.
+pub fn clone_dir(base: &str) -> PathBuf {
+    clone_root().join(base)
+}
+
+pub fn reviewed_path() -> PathBuf {
+    data_dir().join("reviewed.json")
+}
+
+/// The revision last acknowledged for each package base.
+pub fn load_reviewed() -> HashMap<String, String> {
+    fs::read_to_string(reviewed_path())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Remembers that `revision` of `base` was reviewed and acknowledged.
+pub fn record_reviewed(base: &str, revision: &str) -> std::io::Result<()> {
+    let mut reviewed = load_reviewed();
+    reviewed.insert(base.to_string(), revision.to_string());
+
+    let path = reviewed_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&reviewed)?)
+}
+
+/// Runs a command to completion; it is killed if the future is dropped.
+async fn output(
+    program: &str,
+    args: &[&str],
+    dir: Option<&Path>,
+) -> Result<std::process::Output, String> {
+    let mut command = tokio::process::Command::new(program);
+    command.env("LC_ALL", "C").args(args).kill_on_drop(true);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))
+}
+
+/// Runs a command to completion, returning stdout or the first line of
+/// stderr.
+async fn run(program: &str, args: &[&str], dir: Option<&Path>) -> Result<String, String> {
+    let output = output(program, args, dir).await?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(stderr
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("failed")
+            .trim_start_matches("error: ")
+            .trim_start_matches("fatal: ")
+            .to_string())
+    }
+}
+
+/// The package base of `name`, which names its AUR repository.
+async fn package_base(name: &str) -> String {
+    run("yay", &["-Si", "--aur", name], None)
+        .await
+        .ok()
+        .and_then(|output| {
+            parser::parse_info(&output)
+                .into_iter()
+                .find(|(key, _)| key == "Package Base")
+                .and_then(|(_, values)| values.into_iter().next())
+        })
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Clones `base`, or brings an earlier clone up to date with the AUR.
+async fn sync_clone(base: &str) -> Result<PathBuf, String> {
+    let dir = clone_dir(base);
+    if dir.join(".git").is_dir() {
+        run("git", &["fetch", "--quiet", "origin", "master"], Some(&dir)).await?;
+        run(
+            "git",
+            &["reset", "--quiet", "--hard", "FETCH_HEAD"],
+            Some(&dir),
+        )
+        .await?;
+    } else {
+        fs::create_dir_all(clone_root()).map_err(|e| e.to_string())?;
+        let url = format!("https://aur.archlinux.org/{}.git", base);
+        let target = dir.to_string_lossy();
+        run("git", &["clone", "--quiet", &url, &target], None).await?;
+    }
+    Ok(dir)
+}
+
+/// `PKGBUILD` and the install scripts `.SRCINFO` names, with their contents.
+fn build_files(dir: &Path) -> Result<Vec<(String, String)>, String> {
+    let read =
+        |name: &str| fs::read_to_string(dir.join(name)).map_err(|e| format!("{}: {}", name, e));
+    let mut files = vec![("PKGBUILD".to_string(), read("PKGBUILD")?)];
+
+    let srcinfo = fs::read_to_string(dir.join(".SRCINFO")).unwrap_or_default();
+    let mut scripts: Vec<&str> = srcinfo
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("install = "))
+        .collect();
+    scripts.sort();
+    scripts.dedup();
+    for script in scripts {
+        // Only files inside the repository.
+        if !script.contains('/') {
+            files.push((script.to_string(), read(script)?));
+        }
+    }
+    Ok(files)
+}
+
+/// Dependencies in `.SRCINFO` that are neither installed nor in the sync
+/// repositories, so they have to come from the AUR as well.
+async fn aur_depends(dir: &Path) -> Vec<String> {
+    let srcinfo = fs::read_to_string(dir.join(".SRCINFO")).unwrap_or_default();
+    let depends = parser::parse_srcinfo_depends(&srcinfo);
+    if depends.is_empty() {
+        return Vec::new();
+    }
+
+    // `-T` prints the dependencies nothing installed satisfies.
+    let mut args = vec!["-T", "--"];
+    args.extend(depends.iter().map(String::as_str));
+    let Ok(missing) = output("pacman", &args, None).await else {
+        return Vec::new();
+    };
+
+    let mut from_aur = Vec::new();
+    for depend in String::from_utf8_lossy(&missing.stdout).lines() {
+        let in_repos = run(
+            "pacman",
+            &["-Sp", "--print-format", "%n", "--", depend],
+            None,
+        )
+        .await
+        .is_ok();
+        if !in_repos {
+            from_aur.push(parser::dependency_name(depend).to_string());
+        }
+    }
+    from_aur
+}
+
+/// AUR packages with a newer version than the one installed.
+pub async fn aur_updates() -> Result<Vec<String>, String> {
+    let output = output("yay", &["-Qua"], None).await?;
+    // Like `pacman -Qu`, it exits 1 with nothing to say when all is current.
+    if !output.status.success() && !output.stderr.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.lines().next().unwrap_or("failed").to_string());
+    }
+    Ok(parser::parse_updates(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// The approved reviews in the order they can be built: every package
+/// after the approved AUR dependencies it needs, each package base once.
+/// Packages not in `picked` were pulled in as dependencies.
+pub fn build_order(approved: &[AurReview], picked: &HashSet<String>) -> Vec<Build> {
+    fn visit<'a>(
+        review: &'a AurReview,
+        approved: &'a [AurReview],
+        visited: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a AurReview>,
+    ) {
+        if !visited.insert(&review.name) {
+            return;
+        }
+        for depend in &review.depends {
+            if let Some(dep) = approved.iter().find(|r| r.name == *depend) {
+                visit(dep, approved, visited, order);
+            }
+        }
+        if !order.iter().any(|r| r.base == review.base) {
+            order.push(review);
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for review in approved {
+        visit(review, approved, &mut visited, &mut order);
+    }
+    order
+        .into_iter()
+        .map(|review| Build {
+            name: review.name.clone(),
+            base: review.base.clone(),
+            revision: review.revision.clone(),
+            dependency: !picked.contains(&review.name),
+        })
+        .collect()
+}
+
+/// The latest comments on the AUR page of `name`.
+async fn comments(name: &str) -> Result<Vec<AurComment>, String> {
+    let url = format!("https://aur.archlinux.org/packages/{}", name);
+    let page = run("curl", &["-fsSL", "--max-time", "15", &url], None).await?;
+    Ok(parser::parse_aur_comments(&page))
+}
+
+/// Fetches the build files of `name` from the AUR and compares them with
+/// the revision reviewed last.
+pub async fn fetch_review(name: &str) -> Result<AurReview, String> {
+    let base = package_base(name).await;
+    let dir = sync_clone(&base).await?;
+    let revision = run("git", &["rev-parse", "HEAD"], Some(&dir))
+        .await
+        .map_err(|_| format!("{} is not in the AUR", base))?
+        .trim()
+        .to_string();
+    let files = build_files(&dir)?;
+    let depends = aur_depends(&dir).await;
+
+    let changes = match load_reviewed().get(&base) {
+        None => Changes::New,
+        Some(reviewed) if *reviewed == revision => Changes::Unchanged,
+        Some(reviewed) => {
+            match run("git", &["diff", "--no-color", reviewed, "HEAD"], Some(&dir)).await {
+                Ok(diff) => Changes::Diff(diff),
+                Err(_) => Changes::Unknown(reviewed.clone()),
+            }
+        }
+    };
+
+    Ok(AurReview {
+        name: name.to_string(),
+        base,
+        revision,
+        files,
+        changes,
+        comments: comments(name).await,
+        depends,
+    })
+}
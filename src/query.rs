@@ -0,0 +1,252 @@
+//! The search box query language.
+//!
+//! Space-separated terms must all match. A term may be qualified with a field
+//! (`name:`, `desc:`, `repo:`, `provider:`, `installed:`), negated with a
+//! leading `!`, and anchored like fzf: `'term` (substring), `^term` (prefix),
+//! `term$` (suffix). Unqualified terms fuzzy-match the name and fall back to
+//! the description, which counts for less. As in fzf, a negated term without
+//! an anchor excludes what contains it as a substring.
+
+use crate::fuzzy::{
+    fold, fold_char, fuzzy_match, fuzzy_match_indices, normalize_target, original_positions,
+    typo_match,
+};
+use crate::managers::Package;
+
+#[cfg(test)]
+mod tests;
+
+/// How much a description match is worth compared to a name match.
+const DESC_WEIGHT: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The name, falling back to the description.
+    Any,
+    Name,
+    Desc,
+    Repo,
+    Provider,
+    Installed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Fuzzy,
+    Substring,
+    Prefix,
+    Suffix,
+    /// `^term$`: the whole field.
+    Equal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub field: Field,
+    pub mode: Mode,
+    pub negated: bool,
+    /// Folded text with field and anchors stripped.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+fn parse_term(token: &str) -> Option<Term> {
+    let (negated, token) = match token.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+
+    let (field, token) = match token.split_once(':') {
+        Some(("name", rest)) => (Field::Name, rest),
+        Some(("desc", rest)) => (Field::Desc, rest),
+        Some(("repo", rest)) => (Field::Repo, rest),
+        Some(("provider", rest)) => (Field::Provider, rest),
+        Some(("installed", rest)) => (Field::Installed, rest),
+        _ => (Field::Any, token),
+    };
+
+    let (mode, text) = if let Some(rest) = token.strip_prefix('\'') {
+        (Mode::Substring, rest)
+    } else if let Some(rest) = token.strip_prefix('^') {
+        match rest.strip_suffix('$') {
+            Some(inner) => (Mode::Equal, inner),
+            None => (Mode::Prefix, rest),
+        }
+    } else if let Some(rest) = token.strip_suffix('$') {
+        (Mode::Suffix, rest)
+    } else if negated {
+        // A subsequence is in far too many names to exclude by.
+        (Mode::Substring, token)
+    } else {
+        (Mode::Fuzzy, token)
+    };
+
+    if text.is_empty() {
+        return None;
+    }
+    Some(Term {
+        field,
+        mode,
+        negated,
+        text: fold(text),
+    })
+}
+
+/// Matches `text` against `value` as `mode` says, returning a score and the
+/// matched character positions. `typos` lets fuzzy terms match misspellings.
+fn match_text(mode: Mode, text: &str, value: &str, typos: bool) -> Option<(f64, Vec<usize>)> {
+    if mode == Mode::Fuzzy {
+        return fuzzy_match_indices(text, value)
+            .or_else(|| typos.then(|| typo_match(text, value)).flatten())
+            .map(|m| (m.score, m.indices));
+    }
+
+    let (mut chars, mut origin) = (Vec::new(), Vec::new());
+    normalize_target(value, &mut chars, &mut origin);
+    let value: Vec<char> = chars.into_iter().map(fold_char).collect();
+    let text: Vec<char> = text.chars().collect();
+    if text.len() > value.len() {
+        return None;
+    }
+
+    let start = match mode {
+        Mode::Prefix => value.starts_with(&text).then_some(0),
+        Mode::Suffix => value.ends_with(&text).then_some(value.len() - text.len()),
+        Mode::Equal => (value == text).then_some(0),
+        _ => value.windows(text.len()).position(|w| w == text),
+    }?;
+
+    // Anchored matches are exact, so only how much of the field they cover varies.
+    let score = 0.5 + 0.5 * text.len() as f64 / value.len() as f64;
+    let positions: Vec<usize> = (start..start + text.len()).collect();
+    Some((score, original_positions(&positions, &origin)))
+}
+
+/// Whether a filter term like `repo:` matches; no score or positions needed.
+fn matches_field(mode: Mode, text: &str, value: &str) -> bool {
+    match mode {
+        Mode::Fuzzy => fuzzy_match(text, value) > 0.0,
+        _ => match_text(mode, text, value, false).is_some(),
+    }
+}
+
+impl Term {
+    /// Score and name positions for a positive match; filters score 1.0.
+    fn matches(&self, pkg: &Package, typos: bool) -> Option<(f64, Vec<usize>)> {
+        let description = |mode| {
+            // Fuzzy subsequences hit nearly any long text, so descriptions
+            // need the term as a substring.
+            let mode = if mode == Mode::Fuzzy {
+                Mode::Substring
+            } else {
+                mode
+            };
+            match_text(mode, &self.text, &pkg.description, false)
+                .map(|(score, _)| (score * DESC_WEIGHT, Vec::new()))
+        };
+
+        match self.field {
+            Field::Any => match_text(self.mode, &self.text, &pkg.id.name, typos)
+                .or_else(|| description(self.mode)),
+            Field::Name => match_text(self.mode, &self.text, &pkg.id.name, typos),
+            Field::Desc => description(self.mode),
+            Field::Repo => {
+                matches_field(self.mode, &self.text, &pkg.id.repo).then(|| (1.0, vec![]))
+            }
+            Field::Provider => {
+                matches_field(self.mode, &self.text, &pkg.id.provider).then(|| (1.0, vec![]))
+            }
+            Field::Installed => {
+                let wanted = matches!(self.text.as_str(), "yes" | "y" | "true" | "1");
+                (pkg.installed == wanted).then(|| (1.0, Vec::new()))
+            }
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        matches!(self.field, Field::Any | Field::Name | Field::Desc)
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        Self {
+            terms: input.split_whitespace().filter_map(parse_term).collect(),
+        }
+    }
+
+    /// Plain words to hand to the backends' own search, e.g. `pacman -Ss`.
+    pub fn search_words(&self) -> String {
+        self.terms
+            .iter()
+            .filter(|t| t.is_text() && !t.negated)
+            .map(|t| t.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The first plain fuzzy name term, which every match must contain as a
+    /// subsequence of its name when descriptions are unknown.
+    pub fn name_term(&self) -> Option<&str> {
+        self.terms
+            .iter()
+            .find(|t| {
+                matches!(t.field, Field::Any | Field::Name) && t.mode == Mode::Fuzzy && !t.negated
+            })
+            .map(|t| t.text.as_str())
+    }
+
+    /// Whether results from `provider` could pass the `provider:` terms.
+    pub fn allows_provider(&self, provider: &str) -> bool {
+        self.terms
+            .iter()
+            .filter(|t| t.field == Field::Provider)
+            .all(|t| matches_field(t.mode, &t.text, provider) != t.negated)
+    }
+
+    /// Scores `pkg` and records the matched name positions; `false` drops it.
+    pub fn apply(&self, pkg: &mut Package) -> bool {
+        self.apply_with(pkg, false)
+    }
+
+    /// Like `apply`, but fuzzy name terms also accept a few typos. Meant as a
+    /// fallback once strict matching found nothing.
+    pub fn apply_tolerant(&self, pkg: &mut Package) -> bool {
+        self.apply_with(pkg, true)
+    }
+
+    fn apply_with(&self, pkg: &mut Package, typos: bool) -> bool {
+        let mut total = 0.0;
+        let mut scored = 0;
+        let mut matched = Vec::new();
+
+        for term in &self.terms {
+            // Typos never widen what a negated term excludes.
+            match (term.matches(pkg, typos && !term.negated), term.negated) {
+                (Some(_), true) | (None, false) => return false,
+                (None, true) => {}
+                (Some((score, indices)), false) => {
+                    if term.is_text() {
+                        total += score;
+                        scored += 1;
+                    }
+                    matched.extend(indices);
+                }
+            }
+        }
+
+        matched.sort_unstable();
+        matched.dedup();
+        pkg.score = if scored == 0 {
+            1.0
+        } else {
+            total / scored as f64
+        };
+        pkg.matched = matched;
+        pkg.score > 0.01
+    }
+}
@@ -0,0 +1,40 @@
+//! Parses queries and applies them to hand-made packages.
+
+use super::*;
+use crate::managers::PackageId;
+
+fn package(name: &str, description: &str) -> Package {
+    Package::new(
+        PackageId::new("pacman", "extra", name, "1.0-1"),
+        description,
+    )
+}
+
+/// Names of `packages` that `query` keeps.
+fn kept(query: &str, packages: &[Package]) -> Vec<String> {
+    let query = Query::parse(query);
+    packages
+        .iter()
+        .cloned()
+        .filter_map(|mut pkg| query.apply(&mut pkg).then_some(pkg.id.name))
+        .collect()
+}
+
+#[test]
+fn negated_terms_exclude_substrings_only() {
+    let packages = [
+        package("gtk3", "GObject-based multi-platform GUI toolkit"),
+        package("gnome-textkit", "Text editing widgets"),
+        package("libgtkmm", "C++ bindings for GTK"),
+    ];
+
+    // `gnome-textkit` holds g, t and k in order, but not `gtk`.
+    assert_eq!(kept("!gtk", &packages), ["gnome-textkit"]);
+    assert_eq!(Query::parse("!gtk").terms[0].mode, Mode::Substring);
+}
+
+#[test]
+fn anchored_negated_terms_keep_their_anchor() {
+    let packages = [package("gtk3", ""), package("libgtkmm", "")];
+    assert_eq!(kept("!^gtk", &packages), ["libgtkmm"]);
+}
@@ -0,0 +1,109 @@
+//! Blends string similarity with popularity and usage signals.
+
+use crate::config::RankingWeights;
+use crate::history::{self, selections};
+use crate::managers::{OperationKind, Package};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Per-signal contributions to a result's final score, shown in the details pane.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rank {
+    /// `(signal, value in 0..=1, weight)` in display order.
+    pub signals: Vec<(&'static str, f64, f64)>,
+    pub total: f64,
+}
+
+/// What the user has done before: installs through trx and basket picks.
+#[derive(Debug, Clone, Default)]
+pub struct Usage {
+    pub installs: HashMap<String, u32>,
+    pub selections: HashMap<String, u32>,
+}
+
+impl Usage {
+    pub fn load() -> Self {
+        let mut installs: HashMap<String, u32> = HashMap::new();
+        for entry in history::load() {
+            if entry.success && entry.operation.kind == OperationKind::Install {
+                for name in &entry.operation.packages {
+                    *installs.entry(name.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        Self {
+            installs,
+            selections: selections::load(),
+        }
+    }
+}
+
+/// Maps a count onto `0..1`, flattening quickly: 1 → 0.33, 4 → 0.67.
+fn saturate(count: u32) -> f64 {
+    let count = count as f64;
+    count / (count + 2.0)
+}
+
+/// Log-scales `value` so that `max` maps to 1.0.
+fn log_scale(value: f64, max: f64) -> f64 {
+    ((1.0 + value.max(0.0)).ln() / (1.0 + max).ln()).min(1.0)
+}
+
+pub fn rank(pkg: &Package, usage: &Usage, weights: &RankingWeights) -> Rank {
+    let name = pkg.id.name.as_str();
+    let signals = vec![
+        ("Match", pkg.score, weights.matching),
+        (
+            "Votes",
+            pkg.votes.map_or(0.0, |v| log_scale(v as f64, 10_000.0)),
+            weights.votes,
+        ),
+        (
+            "Popularity",
+            pkg.popularity.map_or(0.0, |p| log_scale(p, 100.0)),
+            weights.popularity,
+        ),
+        (
+            "Installed",
+            if pkg.installed { 1.0 } else { 0.0 },
+            weights.installed,
+        ),
+        (
+            "Installed via trx",
+            saturate(usage.installs.get(name).copied().unwrap_or(0)),
+            weights.history,
+        ),
+        (
+            "Picked before",
+            saturate(usage.selections.get(name).copied().unwrap_or(0)),
+            weights.selections,
+        ),
+        (
+            "Official repo",
+            if pkg.id.provider == "pacman" {
+                1.0
+            } else {
+                0.0
+            },
+            weights.official,
+        ),
+    ];
+
+    let total = signals
+        .iter()
+        .map(|(_, value, weight)| value * weight)
+        .sum();
+    Rank { signals, total }
+}
+
+/// Re-scores fuzzy-matched results with every ranking signal.
+pub fn rank_all(packages: &mut [Package], usage: &Usage, weights: &RankingWeights) {
+    for pkg in packages {
+        let rank = rank(pkg, usage, weights);
+        pkg.score = rank.total;
+        pkg.rank = Some(rank);
+    }
+}
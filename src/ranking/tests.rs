@@ -0,0 +1,151 @@
+//! How each ranking signal moves a result, alone and blended.
+
+use super::*;
+use crate::managers::PackageId;
+use crate::query::Query;
+
+fn package(provider: &str, name: &str) -> Package {
+    let mut pkg = Package::new(PackageId::new(provider, provider, name, "1.0-1"), "");
+    pkg.score = 0.8;
+    pkg
+}
+
+/// Names in the order `rank_all` puts them, best first.
+fn ranked(mut packages: Vec<Package>, usage: &Usage, weights: &RankingWeights) -> Vec<String> {
+    rank_all(&mut packages, usage, weights);
+    packages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    packages.into_iter().map(|p| p.id.name).collect()
+}
+
+#[test]
+fn an_exact_name_outranks_a_longer_match() {
+    let query = Query::parse("ripgrep");
+    let mut packages = vec![package("aur", "ripgrep-all"), package("aur", "ripgrep")];
+    for pkg in &mut packages {
+        assert!(query.apply(pkg));
+    }
+    let order = ranked(packages, &Usage::default(), &RankingWeights::default());
+    assert_eq!(order, ["ripgrep", "ripgrep-all"]);
+}
+
+#[test]
+fn votes_and_popularity_lift_equal_matches() {
+    let weights = RankingWeights::default();
+    let mut voted = package("aur", "voted");
+    voted.votes = Some(500);
+    let order = ranked(
+        vec![package("aur", "plain"), voted],
+        &Usage::default(),
+        &weights,
+    );
+    assert_eq!(order, ["voted", "plain"]);
+
+    let mut popular = package("aur", "popular");
+    popular.popularity = Some(5.0);
+    let order = ranked(
+        vec![package("aur", "plain"), popular],
+        &Usage::default(),
+        &weights,
+    );
+    assert_eq!(order, ["popular", "plain"]);
+}
+
+#[test]
+fn installed_and_official_packages_come_first() {
+    let weights = RankingWeights::default();
+    let mut installed = package("aur", "installed");
+    installed.installed = true;
+    let order = ranked(
+        vec![package("aur", "plain"), installed],
+        &Usage::default(),
+        &weights,
+    );
+    assert_eq!(order, ["installed", "plain"]);
+
+    let order = ranked(
+        vec![package("aur", "from-aur"), package("pacman", "official")],
+        &Usage::default(),
+        &weights,
+    );
+    assert_eq!(order, ["official", "from-aur"]);
+}
+
+#[test]
+fn past_installs_and_picks_count_more_the_more_often() {
+    let weights = RankingWeights::default();
+    let usage = Usage {
+        installs: HashMap::from([("often".to_string(), 4), ("once".to_string(), 1)]),
+        selections: HashMap::new(),
+    };
+    let order = ranked(
+        vec![
+            package("aur", "never"),
+            package("aur", "once"),
+            package("aur", "often"),
+        ],
+        &usage,
+        &weights,
+    );
+    assert_eq!(order, ["often", "once", "never"]);
+
+    let usage = Usage {
+        installs: HashMap::new(),
+        selections: HashMap::from([("picked".to_string(), 2)]),
+    };
+    let order = ranked(
+        vec![package("aur", "never"), package("aur", "picked")],
+        &usage,
+        &weights,
+    );
+    assert_eq!(order, ["picked", "never"]);
+}
+
+#[test]
+fn a_much_better_match_beats_every_other_signal_by_default() {
+    let mut favourite = package("pacman", "favourite");
+    favourite.score = 0.3;
+    favourite.installed = true;
+    favourite.votes = Some(10_000);
+    favourite.popularity = Some(100.0);
+    let usage = Usage {
+        installs: HashMap::from([("favourite".to_string(), 50)]),
+        selections: HashMap::from([("favourite".to_string(), 50)]),
+    };
+
+    let order = ranked(
+        vec![favourite, package("aur", "matching")],
+        &usage,
+        &RankingWeights::default(),
+    );
+    assert_eq!(order, ["matching", "favourite"]);
+}
+
+#[test]
+fn weights_decide_how_much_a_signal_counts() {
+    let mut voted = package("aur", "voted");
+    voted.votes = Some(10_000);
+    voted.score = 0.5;
+    let packages = vec![package("aur", "matching"), voted];
+
+    let usage = Usage::default();
+    let order = ranked(packages.clone(), &usage, &RankingWeights::default());
+    assert_eq!(order, ["matching", "voted"]);
+
+    let weights = RankingWeights {
+        votes: 1.0,
+        ..RankingWeights::default()
+    };
+    let order = ranked(packages.clone(), &usage, &weights);
+    assert_eq!(order, ["voted", "matching"]);
+
+    // A zero weight turns the signal off entirely.
+    let weights = RankingWeights {
+        votes: 0.0,
+        ..RankingWeights::default()
+    };
+    let mut packages = packages;
+    rank_all(&mut packages, &usage, &weights);
+    assert_eq!(packages[1].score, 0.5);
+    let rank = packages[1].rank.as_ref().unwrap();
+    assert_eq!(rank.signals[1], ("Votes", 1.0, 0.0));
+}
@@ -1,39 +1,330 @@
-use crate::ui::{draw::draw_ui, input::InputMode};
+use crate::ui::{
+    draw::{self, draw_ui},
+    event::{EventSource, Message},
+    input::InputMode,
+    pty::{JobStatus, PtyJob},
+};
 use color_eyre::Result;
+use futures::future::{BoxFuture, join_all};
 use ratatui::{
-    DefaultTerminal,
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    Terminal,
+    backend::Backend as TerminalBackend,
+    crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::Rect,
     widgets::ListState,
 };
-use std::collections::HashSet;
-use std::sync::mpsc::{Receiver, Sender};
-use std::thread;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::task::AbortHandle;
+
+use crate::config;
+use crate::fuzzy::{IncrementalMatcher, fuzzy_match_indices, score_batch};
+use crate::history::pacman_log::{self, LogEvent};
+use crate::history::{self, HistoryEntry, selections};
+use crate::managers::analysis::{self, LocalPackage, RemovalImpact};
+use crate::managers::backend::{Backend, package_index};
+use crate::managers::cache::{self, CacheGroup, CachedPackage, CleanupPolicy};
+use crate::managers::details::PackageDetails;
+use crate::managers::files;
+use crate::managers::manifest::{self, ManifestDiff};
+use crate::managers::review::{self, AurReview};
+use crate::managers::{self, OperationKind, Package, PackageId};
+use crate::query::Query;
+use crate::ranking::{self, Usage};
+
+type JobResult = Result<Option<PtyJob>, Box<dyn std::error::Error>>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Search,
+    Orphans,
+    Leaves,
+    History,
+    Downgrade,
+    Cache,
+    Manifest,
+    Basket,
+    Owners,
+}
+
+/// Package cache contents and the archives the current policy would delete.
+pub struct CacheView {
+    pub groups: Vec<CacheGroup>,
+    pub policy: CleanupPolicy,
+    pub doomed: HashSet<std::path::PathBuf>,
+    pub confirm: bool,
+}
+
+impl CacheView {
+    pub fn total_size(&self) -> u64 {
+        self.groups.iter().map(CacheGroup::size).sum()
+    }
+
+    /// Archives the cleanup deletes; `doomed` also holds their signatures.
+    pub fn doomed_archives(&self) -> impl Iterator<Item = &CachedPackage> {
+        self.groups
+            .iter()
+            .flat_map(|g| &g.versions)
+            .filter(|p| self.doomed.contains(&p.path))
+    }
+
+    pub fn doomed_size(&self) -> u64 {
+        self.doomed_archives().map(|p| p.size).sum()
+    }
+
+    fn replan(&mut self) {
+        self.doomed = cache::plan_cleanup(&self.groups, self.policy);
+        self.confirm = false;
+    }
+}
+
+/// The Files tab of the details pane: what a package installs, as a tree.
+pub struct FilesView {
+    pub id: PackageId,
+    /// `None` until pacman has answered.
+    pub paths: Option<Result<Vec<String>, String>>,
+    pub filter: String,
+    /// Keys edit `filter` instead of moving the cursor.
+    pub filtering: bool,
+    /// Directories whose contents are hidden.
+    pub collapsed: HashSet<String>,
+    pub selected: usize,
+}
+
+/// One row of the file tree.
+pub struct FileRow<'a> {
+    pub path: &'a str,
+    /// The last path component; directories keep their trailing `/`.
+    pub name: &'a str,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub collapsed: bool,
+    /// Character positions in `name` matched by the filter.
+    pub matched: Vec<usize>,
+}
+
+impl FilesView {
+    fn new(id: PackageId) -> Self {
+        Self {
+            id,
+            paths: None,
+            filter: String::new(),
+            filtering: false,
+            collapsed: HashSet::new(),
+            selected: 0,
+        }
+    }
+
+    /// The visible tree: every path outside collapsed directories, or with a
+    /// filter, the files fuzzy-matching it and the directories leading there.
+    pub fn rows(&self) -> Vec<FileRow<'_>> {
+        let Some(Ok(paths)) = &self.paths else {
+            return Vec::new();
+        };
+        let filter = self.filter.trim();
+
+        if filter.is_empty() {
+            return paths
+                .iter()
+                .filter(|path| {
+                    !self
+                        .collapsed
+                        .iter()
+                        .any(|dir| path.len() > dir.len() && path.starts_with(dir.as_str()))
+                })
+                .map(|path| self.row(path, &[]))
+                .collect();
+        }
+
+        let mut matched: HashMap<&str, Vec<usize>> = HashMap::new();
+        for path in paths.iter().filter(|p| !p.ends_with('/')) {
+            if let Some(m) = fuzzy_match_indices(filter, path) {
+                for (i, _) in path.match_indices('/').skip(1) {
+                    matched.entry(&path[..=i]).or_default();
+                }
+                matched.insert(path, m.indices);
+            }
+        }
+        paths
+            .iter()
+            .filter_map(|path| Some(self.row(path, matched.get(path.as_str())?)))
+            .collect()
+    }
+
+    fn row<'a>(&self, path: &'a str, matched: &[usize]) -> FileRow<'a> {
+        let trimmed = path.trim_end_matches('/');
+        let start = trimmed.rfind('/').map_or(0, |i| i + 1);
+        let offset = path[..start].chars().count();
+        FileRow {
+            path,
+            name: &path[start..],
+            depth: trimmed.matches('/').count().saturating_sub(1),
+            is_dir: path.ends_with('/'),
+            collapsed: self.collapsed.contains(path),
+            matched: matched
+                .iter()
+                .filter(|&&i| i >= offset)
+                .map(|i| i - offset)
+                .collect(),
+        }
+    }
+
+    /// Keeps the cursor on a row after the tree changed shape.
+    fn clamp(&mut self) {
+        self.selected = self.selected.min(self.rows().len().saturating_sub(1));
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        self.selected = self.selected.saturating_add_signed(delta);
+        self.clamp();
+    }
+
+    /// Folds or unfolds the directory under the cursor.
+    fn toggle_selected(&mut self) {
+        let Some(dir) = self
+            .rows()
+            .get(self.selected)
+            .filter(|row| row.is_dir)
+            .map(|row| row.path.to_string())
+        else {
+            return;
+        };
+        if !self.collapsed.remove(&dir) {
+            self.collapsed.insert(dir);
+        }
+        self.clamp();
+    }
+}
+
+/// An imported manifest compared against this system.
+pub struct ManifestView {
+    pub path: String,
+    pub diff: ManifestDiff,
+}
+
+impl ManifestView {
+    /// Missing entries first, then extra ones, as `(is_missing, provider, name)`.
+    pub fn rows(&self) -> impl Iterator<Item = (bool, &str, &str)> {
+        let missing = self
+            .diff
+            .missing
+            .iter()
+            .map(|(p, n)| (true, p.as_str(), n.as_str()));
+        let extra = self
+            .diff
+            .extra
+            .iter()
+            .map(|(p, n)| (false, p.as_str(), n.as_str()));
+        missing.chain(extra)
+    }
+}
+
+/// AUR packages waiting for the user to read their build files; nothing
+/// is built until each has been approved or skipped.
+pub struct ReviewView {
+    /// Builds the approved packages once the last one is through.
+    backend: Arc<dyn Backend>,
+    /// Packages still to review, the one shown first.
+    pub pending: VecDeque<String>,
+    /// What the user asked for; the rest are AUR dependencies.
+    picked: HashSet<String>,
+    /// Everything queued so far, so a dependency is reviewed only once.
+    queued: HashSet<String>,
+    approved: Vec<AurReview>,
+    /// What is shown for the first pending package; `None` while fetching.
+    pub current: Option<Result<AurReview, String>>,
+    pub scroll: u16,
+}
 
-use crate::managers::{self, Package};
+/// Cached archives offered for the package the downgrade view was opened on.
+pub struct DowngradeChoice {
+    pub package: String,
+    pub installed: Option<String>,
+    pub versions: Vec<CachedPackage>,
+    return_selected: usize,
+}
+
+/// A basket operation waiting for the user to press `y`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Confirm {
+    Install,
+    Remove,
+}
+
+/// Which timeline the history view shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HistorySource {
+    /// Operations trx ran itself.
+    Trx,
+    /// Every transaction in pacman.log.
+    System,
+}
 
 pub struct App {
     pub input: String,
     pub character_index: usize,
     pub input_mode: InputMode,
     pub packages: Vec<Package>,
-    pub checked: Vec<bool>,
-    pub selected_names: HashSet<String>,
+    /// Search results grouped by name, preferred source first.
+    pub results: Vec<Vec<Package>>,
+    /// Names whose other sources are listed below the preferred one.
+    pub expanded: HashSet<String>,
+    /// Packages queued for an operation; survives new searches.
+    pub basket: Vec<Package>,
+    pub confirm: Option<Confirm>,
     pub selected: usize,
     pub list_state: ListState,
     pub messages: Vec<String>,
     pub loading: bool,
-    pub details: Option<std::collections::HashMap<String, String>>,
-    pub last_selected: usize,
-    result_tx: Sender<Vec<Package>>,
-    result_rx: Receiver<Vec<Package>>,
+    pub details: Option<PackageDetails>,
+    /// The package `details` belong to, or are being loaded for.
+    details_for: Option<PackageId>,
+    pub details_loading: bool,
+    /// Lines the details pane is scrolled down by.
+    pub details_scroll: u16,
+    /// The details pane's Files tab, while it is open.
+    pub files: Option<FilesView>,
+    files_task: Option<AbortHandle>,
+    /// Details lookups in flight, for the selection and its neighbours.
+    details_tasks: HashMap<PackageId, AbortHandle>,
+    pub view: View,
+    pub local_db: Vec<LocalPackage>,
+    pub impact: Option<RemovalImpact>,
+    pub history: Vec<HistoryEntry>,
+    pub history_filtered: Vec<usize>,
+    pub history_source: HistorySource,
+    pub system_log: Vec<LogEvent>,
+    pub downgrade: Option<DowngradeChoice>,
+    pub cache: Option<CacheView>,
+    pub manifest: Option<ManifestView>,
+    /// Build files shown before AUR packages are built.
+    pub review: Option<ReviewView>,
+    review_task: Option<AbortHandle>,
+    pub job: Option<PtyJob>,
+    pub job_visible: bool,
+    job_queue: VecDeque<Box<dyn FnOnce() -> JobResult>>,
+    job_reported: bool,
+    tx: UnboundedSender<Message>,
+    rx: UnboundedReceiver<Message>,
+    quit: bool,
+    /// Package sources to search and install from.
+    backends: Vec<Arc<dyn Backend>>,
     last_input_time: Instant,
     pending_search: bool,
     last_search_query: String,
+    /// Narrows full-index matches from one keystroke to the next.
+    index_matcher: Arc<Mutex<IncrementalMatcher>>,
 }
 
 impl App {
-    pub fn new(result_tx: Sender<Vec<Package>>, result_rx: Receiver<Vec<Package>>) -> Self {
+    pub fn new(
+        tx: UnboundedSender<Message>,
+        rx: UnboundedReceiver<Message>,
+        backends: Vec<Arc<dyn Backend>>,
+    ) -> Self {
         let mut list_state = ListState::default();
         list_state.select(None);
 
@@ -43,18 +334,44 @@ impl App {
             messages: Vec::new(),
             character_index: 0,
             packages: Vec::new(),
-            checked: Vec::new(),
-            selected_names: HashSet::new(),
+            results: Vec::new(),
+            expanded: HashSet::new(),
+            basket: Vec::new(),
+            confirm: None,
             selected: 0,
             list_state,
             loading: false,
             details: None,
-            last_selected: usize::MAX,
-            result_tx,
-            result_rx,
+            details_for: None,
+            details_loading: false,
+            details_scroll: 0,
+            files: None,
+            files_task: None,
+            details_tasks: HashMap::new(),
+            view: View::Search,
+            local_db: Vec::new(),
+            impact: None,
+            history: Vec::new(),
+            history_filtered: Vec::new(),
+            history_source: HistorySource::Trx,
+            system_log: Vec::new(),
+            downgrade: None,
+            cache: None,
+            manifest: None,
+            review: None,
+            review_task: None,
+            job: None,
+            job_visible: false,
+            job_queue: VecDeque::new(),
+            job_reported: false,
+            tx,
+            rx,
+            quit: false,
+            backends,
             last_input_time: Instant::now(),
             pending_search: false,
             last_search_query: String::new(),
+            index_matcher: Arc::new(Mutex::new(IncrementalMatcher::default())),
         }
     }
 
@@ -105,6 +422,23 @@ impl App {
     fn check_and_execute_search(&mut self) {
         const DEBOUNCE_MS: u64 = 100;
 
+        if self.view == View::History {
+            if self.pending_search {
+                self.pending_search = false;
+                self.filter_history();
+            }
+            return;
+        }
+
+        // pacman reads its file databases per lookup; wait for Enter.
+        if self.view == View::Owners {
+            if self.pending_search && matches!(self.input_mode, InputMode::Normal) {
+                self.pending_search = false;
+                self.find_owners();
+            }
+            return;
+        }
+
         if self.pending_search
             && self.last_input_time.elapsed() >= Duration::from_millis(DEBOUNCE_MS)
         {
@@ -115,166 +449,1418 @@ impl App {
                 self.pending_search = false;
                 self.loading = true;
 
-                let tx = self.result_tx.clone();
+                let tx = self.tx.clone();
+                let matcher = Arc::clone(&self.index_matcher);
+                let backends = self.backends.clone();
+                tokio::spawn(async move {
+                    let groups = search(&query, backends, matcher).await;
+                    let _ = tx.send(Message::SearchResults { query, groups });
+                });
+            } else if query.is_empty() {
+                self.pending_search = false;
+                self.packages.clear();
+                self.messages.clear();
+                self.loading = false;
+            }
+        }
+    }
 
-                thread::spawn(move || {
-                    let pac_handle = thread::spawn({
-                        let q = query.clone();
-                        move || managers::search_pacman(&q)
-                    });
+    /// Installs everything in the basket, whichever search it was picked
+    /// from. Packages leave the basket once their install has succeeded.
+    fn run_command(&mut self) {
+        let installable: Vec<Package> = self
+            .basket
+            .iter()
+            .filter(|p| self.installable(p))
+            .cloned()
+            .collect();
 
-                    let aur_handle = thread::spawn({
-                        let q = query.clone();
-                        move || managers::search_aur(&q)
-                    });
+        for (provider, names) in group_by_provider(&installable) {
+            self.install_with(provider, names);
+        }
+    }
+
+    /// Removes everything in the basket from the system. Packages leave
+    /// the basket once their removal has succeeded.
+    fn remove_basket(&mut self) {
+        for (provider, names) in group_by_provider(&self.basket) {
+            self.queue_job(move || managers::pacman_removal(&provider, &names));
+        }
+    }
+
+    /// Whether a backend can install `pkg`; packages picked from the local
+    /// database have none.
+    pub fn installable(&self, pkg: &Package) -> bool {
+        self.backend(&pkg.id.provider).is_some()
+    }
+
+    pub fn in_basket(&self, pkg: &Package) -> bool {
+        self.basket.iter().any(|p| p.id == pkg.id)
+    }
+
+    fn toggle_basket(&mut self, pkg: Package) {
+        if self.in_basket(&pkg) {
+            self.basket.retain(|p| p.id != pkg.id);
+        } else {
+            let _ = selections::record(&pkg.id.name);
+            self.basket.push(pkg);
+        }
+    }
+
+    /// Asks before acting on the whole basket; `y` runs the action.
+    fn request_confirm(&mut self, action: Confirm) {
+        if !self.basket.is_empty() {
+            self.confirm = Some(action);
+        }
+    }
+
+    fn open_basket(&mut self) {
+        self.view = View::Basket;
+        self.impact = None;
+        self.selected = 0;
+        self.list_state
+            .select((!self.basket.is_empty()).then_some(0));
+    }
+
+    fn remove_from_basket(&mut self) {
+        if self.selected < self.basket.len() {
+            self.basket.remove(self.selected);
+        }
+        self.selected = self.selected.min(self.basket.len().saturating_sub(1));
+        self.list_state
+            .select((!self.basket.is_empty()).then_some(self.selected));
+    }
 
-                    let mut all = pac_handle.join().unwrap_or_default();
-                    all.extend(aur_handle.join().unwrap_or_default());
+    fn backend(&self, provider: &str) -> Option<Arc<dyn Backend>> {
+        self.backends.iter().find(|b| b.name() == provider).cloned()
+    }
 
-                    all.sort_by(|a, b| {
-                        b.score
-                            .partial_cmp(&a.score)
-                            .unwrap_or(std::cmp::Ordering::Equal)
+    /// Installs `names` through the backend registered for `provider`;
+    /// packages built from source are reviewed first.
+    fn install_with(&mut self, provider: String, names: HashSet<String>) {
+        let Some(backend) = self.backend(&provider) else {
+            self.messages = vec![format!("No backend for provider {}", provider)];
+            return;
+        };
+        let mut queue: Vec<String> = names.iter().cloned().collect();
+        queue.sort();
+        match queue.first().and_then(|name| backend.review(name)) {
+            Some(lookup) => self.start_review(backend, queue, lookup),
+            None => self.queue_job(move || backend.install(&names)),
+        }
+    }
+
+    /// Shows the build files of `names` one after another, starting with
+    /// `lookup` for the first; names join a review already open.
+    fn start_review(
+        &mut self,
+        backend: Arc<dyn Backend>,
+        names: Vec<String>,
+        lookup: BoxFuture<'static, Result<AurReview, String>>,
+    ) {
+        if let Some(view) = self.review.as_mut() {
+            for name in names {
+                view.picked.insert(name.clone());
+                if view.queued.insert(name.clone()) {
+                    view.pending.push_back(name);
+                }
+            }
+            return;
+        }
+
+        self.fetch_review(names[0].clone(), lookup);
+        self.review = Some(ReviewView {
+            backend,
+            picked: names.iter().cloned().collect(),
+            queued: names.iter().cloned().collect(),
+            pending: names.into(),
+            approved: Vec::new(),
+            current: None,
+            scroll: 0,
+        });
+    }
+
+    fn fetch_review(
+        &mut self,
+        name: String,
+        lookup: BoxFuture<'static, Result<AurReview, String>>,
+    ) {
+        let tx = self.tx.clone();
+        let task = tokio::spawn(async move {
+            let review = lookup.await.map(Box::new);
+            let _ = tx.send(Message::ReviewLoaded { name, review });
+        });
+        self.review_task = Some(task.abort_handle());
+    }
+
+    /// Moves on to the next package, or builds the approved ones once none
+    /// are left.
+    fn next_review(&mut self) {
+        let Some(view) = self.review.as_mut() else {
+            return;
+        };
+        view.pending.pop_front();
+        view.current = None;
+        view.scroll = 0;
+
+        let next = view
+            .pending
+            .front()
+            .and_then(|name| Some((name.clone(), view.backend.review(name)?)));
+        if let Some((name, lookup)) = next {
+            self.fetch_review(name, lookup);
+            return;
+        }
+
+        if let Some(view) = self.review.take() {
+            let builds = review::build_order(&view.approved, &view.picked);
+            if !builds.is_empty() {
+                let backend = view.backend;
+                self.queue_job(move || backend.build(&builds));
+            }
+        }
+    }
+
+    /// Looks up which AUR packages have updates, to review them before
+    /// they are built.
+    fn upgrade_aur(&mut self) {
+        self.messages = vec!["Checking the AUR for updates...".to_string()];
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let updates = review::aur_updates().await;
+            let _ = tx.send(Message::AurUpdates(updates));
+        });
+    }
+
+    /// Records the shown revision as reviewed and approves the package;
+    /// its AUR dependencies are queued for review after it.
+    fn approve_review(&mut self) {
+        let Some(view) = self.review.as_mut() else {
+            return;
+        };
+        let Some(Ok(shown)) = &view.current else {
+            return;
+        };
+        let shown = shown.clone();
+        let recorded = review::record_reviewed(&shown.base, &shown.revision);
+        for depend in &shown.depends {
+            if view.queued.insert(depend.clone()) {
+                view.pending.push_back(depend.clone());
+            }
+        }
+        view.approved.push(shown);
+
+        if let Err(e) = recorded {
+            self.messages = vec![format!("Failed to record the review: {}", e)];
+        }
+        self.next_review();
+    }
+
+    /// Drops the review and everything waiting in it; nothing is built.
+    fn cancel_review(&mut self) {
+        if let Some(task) = self.review_task.take() {
+            task.abort();
+        }
+        if self.review.take().is_some() {
+            self.messages = vec!["Install cancelled".to_string()];
+        }
+    }
+
+    fn handle_review_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.review.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => view.scroll = view.scroll.saturating_add(1),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+            KeyCode::PageDown => view.scroll = view.scroll.saturating_add(10),
+            KeyCode::Char('a') => self.approve_review(),
+            KeyCode::Char('s') => self.next_review(),
+            KeyCode::Esc => self.cancel_review(),
+            KeyCode::Char('q') => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Queues an operation for the job pane; queued jobs run one after another.
+    fn queue_job(&mut self, start: impl FnOnce() -> JobResult + 'static) {
+        self.job_queue.push_back(Box::new(start));
+        if !self.job.as_ref().is_some_and(PtyJob::is_running) {
+            self.start_next_job();
+        }
+    }
+
+    fn start_next_job(&mut self) {
+        while let Some(start) = self.job_queue.pop_front() {
+            match start() {
+                Ok(Some(job)) => {
+                    let tx = self.tx.clone();
+                    let exited = job.exited();
+                    tokio::spawn(async move {
+                        let _ = tx.send(Message::CommandFinished(exited.await));
                     });
+                    self.job = Some(job);
+                    self.job_visible = true;
+                    self.job_reported = false;
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.job_queue.clear();
+                    self.messages = vec![format!("Failed to start command: {}", e)];
+                }
+            }
+        }
+    }
 
-                    all.truncate(50);
+    /// Records the finished job and starts the next queued one.
+    fn finish_job(&mut self, status: JobStatus) {
+        if self.job_reported {
+            return;
+        }
+        self.job_reported = true;
 
-                    let _ = tx.send(all);
+        if let Some(job) = &self.job {
+            let (exit_code, success) = match status {
+                JobStatus::Exited { code, success } => (Some(code), success),
+                _ => (None, false),
+            };
+            let entry = HistoryEntry {
+                timestamp: chrono::Utc::now(),
+                operation: job.operation.clone(),
+                command: job.command_line.clone(),
+                exit_code,
+                success,
+                duration_secs: job.elapsed().as_secs_f64(),
+            };
+            if let Err(e) = history::record(&entry) {
+                self.messages = vec![format!("Failed to write history: {}", e)];
+            }
+            let kind = job.operation.kind;
+            if success && matches!(kind, OperationKind::Install | OperationKind::Remove) {
+                let done = &job.operation;
+                self.basket.retain(|p| {
+                    p.id.provider != done.provider || !done.packages.contains(&p.id.name)
                 });
-            } else if query.is_empty() {
-                self.pending_search = false;
-                self.packages.clear();
-                self.messages.clear();
-                self.loading = false;
+                if self.view == View::Basket {
+                    self.open_basket();
+                }
+            }
+            if self.view == View::History {
+                self.open_history();
+            }
+        }
+
+        if matches!(status, JobStatus::Exited { success: true, .. }) {
+            self.start_next_job();
+        } else {
+            self.job_queue.clear();
+        }
+
+        if self.job.as_ref().is_some_and(PtyJob::is_running) {
+            return;
+        }
+        match self.view {
+            View::Orphans | View::Leaves => self.open_analysis(self.view),
+            View::Cache => self.open_cache(),
+            View::Manifest => {
+                if let Some(path) = self.manifest.as_ref().map(|m| m.path.clone()) {
+                    self.open_manifest(&path);
+                }
             }
+            _ => {}
         }
     }
 
-    fn run_command(
-        &self,
-        terminal: &mut DefaultTerminal,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if self.selected_names.is_empty() {
-            return Ok(());
+    fn handle_job_key(&mut self, key: KeyEvent) {
+        let Some(job) = self.job.as_mut() else {
+            self.job_visible = false;
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.job_visible = false;
+            }
+            KeyCode::PageUp => job.scroll(10),
+            KeyCode::PageDown => job.scroll(-10),
+            // Only once the exit has been recorded in the history.
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') if self.job_reported => {
+                self.job = None;
+                self.job_visible = false;
+            }
+            _ if job.is_running() => job.send_key(key),
+            _ => {}
         }
+    }
 
-        let mut pacman_pkgs = HashSet::new();
-        let mut aur_pkgs = HashSet::new();
+    fn open_history(&mut self) {
+        self.view = View::History;
+        self.impact = None;
+        self.filter_history();
 
-        for name in &self.selected_names {
-            if let Some(pkg) = self.packages.iter().find(|p| p.name == *name) {
-                match pkg.provider.as_str() {
-                    "pacman" => {
-                        pacman_pkgs.insert(name.clone());
-                    }
-                    "aur" => {
-                        aur_pkgs.insert(name.clone());
-                    }
-                    _ => {}
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let history = history::load();
+            let system_log = pacman_log::load();
+            let _ = tx.send(Message::HistoryLoaded {
+                history,
+                system_log,
+            });
+        });
+    }
+
+    fn filter_history(&mut self) {
+        let query = self.input.trim();
+        let summaries: Vec<String> = match self.history_source {
+            HistorySource::Trx => self.history.iter().map(HistoryEntry::summary).collect(),
+            HistorySource::System => self.system_log.iter().map(LogEvent::summary).collect(),
+        };
+        self.history_filtered = if query.is_empty() {
+            (0..summaries.len()).collect()
+        } else {
+            // pacman.log easily holds tens of thousands of events.
+            let mut hits: Vec<usize> = score_batch(query, &summaries, summaries.len())
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect();
+            // Keep the timeline order rather than the match order.
+            hits.sort_unstable();
+            hits
+        };
+
+        self.selected = 0;
+        self.list_state.select(if self.history_filtered.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    pub fn selected_history(&self) -> Option<&HistoryEntry> {
+        if self.history_source != HistorySource::Trx {
+            return None;
+        }
+        self.history_filtered
+            .get(self.selected)
+            .map(|&i| &self.history[i])
+    }
+
+    pub fn selected_log_event(&self) -> Option<&LogEvent> {
+        if self.history_source != HistorySource::System {
+            return None;
+        }
+        self.history_filtered
+            .get(self.selected)
+            .map(|&i| &self.system_log[i])
+    }
+
+    fn rerun_history(&mut self, undo: bool) {
+        let Some(entry) = self.selected_history() else {
+            return;
+        };
+
+        let operation = if undo {
+            match history::undo_operation(&entry.operation) {
+                Some(op) => op,
+                None => {
+                    let reason = match entry.operation.kind {
+                        OperationKind::Downgrade => "Downgrades can't be undone; upgrade with u",
+                        OperationKind::CleanCache => "Deleted cache archives can't be restored",
+                        _ => "Upgrades can't be undone",
+                    };
+                    self.messages = vec![reason.to_string()];
+                    return;
+                }
+            }
+        } else {
+            entry.operation.clone()
+        };
+        // Built from the AUR again, so the build files are reviewed again.
+        // Removals of installed packages ("local") come back from the repositories.
+        if operation.provider == "aur" {
+            match operation.kind {
+                OperationKind::Install => {
+                    let names = operation.packages.into_iter().collect();
+                    self.install_with("aur".to_string(), names);
+                    return;
+                }
+                OperationKind::Upgrade => {
+                    self.upgrade_aur();
+                    return;
                 }
+                _ => {}
             }
         }
+        self.queue_job(move || managers::run_operation(&operation));
+    }
+
+    fn open_downgrade(&mut self) {
+        let Some(pkg) = self.packages.get(self.selected) else {
+            return;
+        };
+        let package = pkg.id.name.clone();
+
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let installed = cache::installed_version(&package);
+            let versions = cache::cached_versions(&package);
+            let _ = tx.send(Message::VersionsLoaded {
+                package,
+                installed,
+                versions,
+            });
+        });
+    }
 
-        if !pacman_pkgs.is_empty() {
-            managers::pacman_installation(terminal, &pacman_pkgs)?;
+    /// Switches to the downgrade view if `package` is still the one selected.
+    fn show_downgrade(
+        &mut self,
+        package: String,
+        installed: Option<String>,
+        versions: Vec<CachedPackage>,
+    ) {
+        let still_selected = self
+            .packages
+            .get(self.selected)
+            .is_some_and(|p| p.id.name == package);
+        if self.view != View::Search || !still_selected {
+            return;
         }
 
-        if !aur_pkgs.is_empty() {
-            managers::aur_installation(terminal, &aur_pkgs)?;
+        self.downgrade = Some(DowngradeChoice {
+            package,
+            installed,
+            versions,
+            return_selected: self.selected,
+        });
+        self.view = View::Downgrade;
+        self.selected = 0;
+        self.list_state.select(Some(0));
+    }
+
+    fn install_cached_version(&mut self) {
+        let Some(archive) = self
+            .downgrade
+            .as_ref()
+            .and_then(|d| d.versions.get(self.selected))
+        else {
+            return;
+        };
+
+        let archives = vec![archive.path.to_string_lossy().to_string()];
+        self.queue_job(move || managers::cache_installation(&archives));
+    }
+
+    /// Switches to the cache view; its contents arrive once the scan is done.
+    fn open_cache(&mut self) {
+        self.view = View::Cache;
+        self.selected = 0;
+        self.list_state.select(Some(0));
+
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let groups = cache::group_archives(cache::scan_all(), &managers::installed_names());
+            let _ = tx.send(Message::CacheScanned(groups));
+        });
+    }
+
+    /// Plans a cleanup of `groups` under the policy the view already had.
+    fn show_cache(&mut self, groups: Vec<CacheGroup>) {
+        if self.view != View::Cache {
+            return;
         }
+        let policy = self.cache.as_ref().map(|c| c.policy).unwrap_or_default();
 
-        Ok(())
+        let mut view = CacheView {
+            groups,
+            policy,
+            doomed: HashSet::new(),
+            confirm: false,
+        };
+        view.replan();
+
+        self.cache = Some(view);
+        self.selected = 0;
+        self.list_state.select(Some(0));
     }
 
-    pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
-        loop {
-            self.check_and_execute_search();
+    fn update_policy(&mut self, update: impl FnOnce(&mut CleanupPolicy)) {
+        if let Some(view) = self.cache.as_mut() {
+            update(&mut view.policy);
+            view.replan();
+        }
+    }
 
-            if let Ok(pkgs) = self.result_rx.try_recv() {
-                self.packages = pkgs;
+    /// Deletes what the dry run showed; the first press only asks for confirmation.
+    fn clean_cache(&mut self, confirmed: bool) {
+        let Some(view) = self.cache.as_mut() else {
+            return;
+        };
+        if view.doomed.is_empty() {
+            return;
+        }
+        if !confirmed {
+            view.confirm = true;
+            return;
+        }
+        view.confirm = false;
 
-                self.checked = self
-                    .packages
-                    .iter()
-                    .map(|p| self.selected_names.contains(&p.name))
-                    .collect();
+        let mut by_source: Vec<(&'static str, Vec<String>)> = Vec::new();
+        for archive in view.doomed_archives() {
+            let paths = std::iter::once(&archive.path)
+                .chain(&archive.signature)
+                .map(|path| path.to_string_lossy().to_string());
+            match by_source.iter_mut().find(|(s, _)| *s == archive.source) {
+                Some((_, existing)) => existing.extend(paths),
+                None => by_source.push((archive.source, paths.collect())),
+            }
+        }
 
-                self.selected = 0;
-                self.loading = false;
+        for (source, paths) in by_source {
+            self.queue_job(move || managers::cache_cleanup(source, &paths));
+        }
+    }
 
-                if !self.packages.is_empty() {
-                    self.list_state.select(Some(0));
+    /// Shows how the manifest at `path` differs from the installed packages.
+    pub fn open_manifest(&mut self, path: &str) {
+        let path = path.to_string();
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let diff = manifest::load(std::path::Path::new(&path))
+                .map(|wanted| manifest::diff_system(&wanted))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(Message::ManifestLoaded { path, diff });
+        });
+    }
+
+    /// Installs every missing manifest entry, one job per provider.
+    fn install_missing(&mut self) {
+        let Some(view) = &self.manifest else {
+            return;
+        };
+
+        let mut groups: Vec<(String, HashSet<String>)> = Vec::new();
+        for (provider, name) in &view.diff.missing {
+            match groups.iter_mut().find(|(p, _)| p == provider) {
+                Some((_, names)) => {
+                    names.insert(name.clone());
+                }
+                None => groups.push((provider.clone(), HashSet::from([name.clone()]))),
+            }
+        }
+
+        for (provider, names) in groups {
+            self.install_with(provider, names);
+        }
+    }
+
+    /// Returns to the search results from any other view.
+    fn leave_view(&mut self) {
+        let mut selected = 0;
+        match self.view {
+            View::Orphans | View::Leaves | View::Owners => {
+                self.set_packages(Vec::new());
+                self.last_search_query.clear();
+            }
+            View::Downgrade => {
+                selected = self.downgrade.take().map_or(0, |d| d.return_selected);
+            }
+            View::Search | View::History | View::Cache | View::Manifest | View::Basket => {}
+        }
+
+        self.view = View::Search;
+        self.impact = None;
+        self.selected = selected.min(self.packages.len().saturating_sub(1));
+        self.list_state
+            .select((!self.packages.is_empty()).then_some(self.selected));
+    }
+
+    fn list_len(&self) -> usize {
+        match self.view {
+            View::History => self.history_filtered.len(),
+            View::Downgrade => self.downgrade.as_ref().map_or(0, |d| d.versions.len()),
+            View::Cache => self.cache.as_ref().map_or(0, |c| c.groups.len()),
+            View::Manifest => self.manifest.as_ref().map_or(0, |m| m.rows().count()),
+            View::Basket => self.basket.len(),
+            _ => self.packages.len(),
+        }
+    }
+
+    /// Search results with every collapsed group reduced to its preferred source.
+    fn visible_results(&self) -> Vec<Package> {
+        self.results
+            .iter()
+            .flat_map(|group| {
+                let shown = if self.expanded.contains(&group[0].id.name) {
+                    group.len()
                 } else {
-                    self.list_state.select(None);
+                    1
+                };
+                group.iter().take(shown).cloned()
+            })
+            .collect()
+    }
+
+    /// Sources of `pkg` hidden in its collapsed group, if `pkg` leads one.
+    pub fn hidden_sources(&self, pkg: &Package) -> usize {
+        if self.view != View::Search || self.expanded.contains(&pkg.id.name) {
+            return 0;
+        }
+        self.results
+            .iter()
+            .find(|group| group[0].id == pkg.id)
+            .map_or(0, |group| group.len() - 1)
+    }
+
+    /// Shows or hides the other sources of the highlighted package.
+    fn toggle_expanded(&mut self) {
+        let Some(pkg) = self.packages.get(self.selected) else {
+            return;
+        };
+        let name = pkg.id.name.clone();
+        if !self.expanded.remove(&name) {
+            self.expanded.insert(name.clone());
+        }
+
+        self.packages = self.visible_results();
+        // Keep the cursor on the group's preferred source.
+        self.selected = self
+            .packages
+            .iter()
+            .position(|p| p.id.name == name)
+            .unwrap_or(0);
+        self.list_state.select(Some(self.selected));
+    }
+
+    /// Names of listed packages that are also in the basket.
+    fn checked_names(&self) -> HashSet<String> {
+        self.packages
+            .iter()
+            .filter(|p| self.in_basket(p))
+            .map(|p| p.id.name.clone())
+            .collect()
+    }
+
+    fn set_packages(&mut self, pkgs: Vec<Package>) {
+        self.packages = pkgs;
+        // Reload details even if the same package ends up selected.
+        self.details_for = None;
+
+        self.selected = 0;
+        self.loading = false;
+
+        if !self.packages.is_empty() {
+            self.list_state.select(Some(0));
+        } else {
+            self.list_state.select(None);
+        }
+
+        self.messages = self
+            .packages
+            .iter()
+            .map(|p| format!("{} {:<15} {}", p.id, p.id.version, p.description))
+            .collect();
+    }
+
+    /// Switches the package list to one of the local database analyses.
+    fn open_analysis(&mut self, view: View) {
+        self.view = view;
+        self.set_packages(Vec::new());
+        self.loading = true;
+        self.messages = vec!["Reading the local package database...".to_string()];
+        self.update_impact();
+
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let db = analysis::local_packages();
+            let _ = tx.send(Message::AnalysisLoaded { view, db });
+        });
+    }
+
+    /// Lists what the analysis for `view` finds in `db`.
+    fn show_analysis(&mut self, view: View, db: Vec<LocalPackage>) {
+        if self.view != view {
+            return;
+        }
+        self.local_db = db;
+
+        let listed = match view {
+            View::Orphans => analysis::orphans(&self.local_db),
+            View::Leaves => analysis::leaves(&self.local_db),
+            _ => Vec::new(),
+        };
+        let pkgs = listed
+            .into_iter()
+            .map(|p| {
+                let mut pkg = Package::new(
+                    PackageId::new("local", "local", &p.name, &p.version),
+                    &p.description,
+                );
+                pkg.installed = true;
+                pkg
+            })
+            .collect();
+
+        self.set_packages(pkgs);
+        if self.packages.is_empty() {
+            self.messages = vec![match view {
+                View::Orphans => "No orphaned packages".to_string(),
+                _ => "No leaf packages".to_string(),
+            }];
+        }
+        self.update_impact();
+    }
+
+    /// Names the analysis acts on: the checked packages, or the highlighted one.
+    fn analysis_targets(&self) -> HashSet<String> {
+        let checked = self.checked_names();
+
+        if !checked.is_empty() {
+            return checked;
+        }
+        self.packages
+            .get(self.selected)
+            .map(|p| HashSet::from([p.id.name.clone()]))
+            .unwrap_or_default()
+    }
+
+    fn update_impact(&mut self) {
+        if !matches!(self.view, View::Orphans | View::Leaves) {
+            self.impact = None;
+            return;
+        }
+
+        let targets = self.analysis_targets();
+        self.impact = if targets.is_empty() {
+            None
+        } else {
+            Some(analysis::removal_impact(&self.local_db, &targets))
+        };
+    }
+
+    /// Removes the checked packages, or every listed orphan when nothing is checked.
+    fn cleanup(&mut self) {
+        let checked = self.checked_names();
+
+        let targets = if !checked.is_empty() {
+            checked
+        } else if self.view == View::Orphans {
+            self.packages.iter().map(|p| p.id.name.clone()).collect()
+        } else {
+            return;
+        };
+
+        self.queue_job(move || managers::pacman_removal("local", &targets));
+    }
+
+    pub async fn run<B: TerminalBackend>(
+        mut self,
+        terminal: &mut Terminal<B>,
+        events: impl EventSource,
+    ) -> Result<()> {
+        events.spawn(self.tx.clone());
+        self.render(terminal)?;
+
+        while let Some(message) = self.next_message().await {
+            self.update(message);
+            if self.quit {
+                break;
+            }
+            self.render(terminal)?;
+        }
+        Ok(())
+    }
+
+    /// Applies one message to the app state. Everything that changes the
+    /// state goes through here; drawing only reads it.
+    pub fn update(&mut self, message: Message) {
+        let shown = (self.view, self.selected);
+        match message {
+            Message::KeyInput(key) => self.handle_key(key),
+            Message::SearchResults { query, groups } => {
+                // Results for a query the user has since typed past are stale.
+                if query == self.last_search_query {
+                    self.view = View::Search;
+                    self.results = groups;
+                    self.expanded.clear();
+                    self.set_packages(self.visible_results());
+                }
+            }
+            Message::DetailsLoaded { id, details } => {
+                self.details_tasks.remove(&id);
+                if self.details_for.as_ref() == Some(&id) {
+                    self.details = details.map(|details| *details);
+                    self.details_loading = false;
                 }
+            }
+            Message::FilesLoaded { id, files } => {
+                if let Some(view) = self.files.as_mut().filter(|view| view.id == id) {
+                    view.paths = Some(files);
+                    self.files_task = None;
+                }
+            }
+            Message::OwnersFound { target, packages } => {
+                // Only the answer for what is typed now.
+                if self.view == View::Owners && target == self.input.trim() {
+                    self.set_packages(packages);
+                    if self.packages.is_empty() {
+                        self.messages = vec![format!("No package owns {}", target)];
+                    }
+                }
+            }
+            Message::AnalysisLoaded { view, db } => self.show_analysis(view, db),
+            Message::HistoryLoaded {
+                history,
+                system_log,
+            } => {
+                if self.view == View::History {
+                    self.history = history;
+                    self.system_log = system_log;
+                    self.filter_history();
+                }
+            }
+            Message::VersionsLoaded {
+                package,
+                installed,
+                versions,
+            } => self.show_downgrade(package, installed, versions),
+            Message::CacheScanned(groups) => self.show_cache(groups),
+            Message::ManifestLoaded { path, diff } => match diff {
+                // A refresh for a manifest view that has since been left.
+                Ok(_)
+                    if self.view != View::Manifest
+                        && self.manifest.as_ref().is_some_and(|m| m.path == path) => {}
+                Ok(diff) => {
+                    self.manifest = Some(ManifestView { path, diff });
+                    self.view = View::Manifest;
+                    self.selected = 0;
+                    self.list_state.select(Some(0));
+                }
+                Err(e) => self.messages = vec![format!("Failed to read {}: {}", path, e)],
+            },
+            Message::AurUpdates(Ok(names)) if names.is_empty() => {
+                self.messages = vec!["AUR packages are up to date".to_string()];
+            }
+            Message::AurUpdates(Ok(names)) => {
+                self.install_with("aur".to_string(), names.into_iter().collect());
+            }
+            Message::AurUpdates(Err(e)) => {
+                self.messages = vec![format!("Failed to check the AUR for updates: {}", e)];
+            }
+            Message::ReviewLoaded { name, review } => {
+                if let Some(view) = self.review.as_mut()
+                    && view.pending.front() == Some(&name)
+                {
+                    view.current = Some(review.map(|review| *review));
+                    self.review_task = None;
+                }
+            }
+            Message::Tick => self.check_and_execute_search(),
+            Message::CommandFinished(status) => self.finish_job(status),
+        }
+        // Another entry's details start from the top.
+        if (self.view, self.selected) != shown {
+            self.details_scroll = 0;
+        }
+        self.request_details();
+    }
 
-                self.messages = self
-                    .packages
-                    .iter()
-                    .map(|p| format!("{} {:<15} {}", p.name, p.version, p.description))
-                    .collect();
+    /// Waits for the next message from the event source or a finished task.
+    pub async fn next_message(&mut self) -> Option<Message> {
+        self.rx.recv().await
+    }
+
+    pub fn render<B: TerminalBackend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        let size = terminal.size()?;
+        let area = Rect::new(0, 0, size.width, size.height);
+        if self.job_visible
+            && let Some(job) = self.job.as_mut()
+        {
+            let pane = draw::job_pane_inner(area);
+            job.resize(pane.height, pane.width);
+        }
+        // Scrolling stops once the last line is in view, also after a resize.
+        self.details_scroll = self
+            .details_scroll
+            .min(draw::details_scroll_limit(self, area));
+        let job_visible = self.job_visible;
+        if let Some(view) = self.review.as_mut() {
+            view.scroll = view
+                .scroll
+                .min(draw::review_scroll_limit(view, area, job_visible));
+        }
+
+        // The list's scroll offset is the only state drawing updates.
+        let mut list_state = std::mem::take(&mut self.list_state);
+        let drawn = terminal.draw(|frame| draw_ui(frame, self, &mut list_state));
+        self.list_state = list_state;
+        drawn?;
+        Ok(())
+    }
+
+    /// The package `details` belong to.
+    pub fn details_target(&self) -> Option<&PackageId> {
+        self.details_for.as_ref()
+    }
+
+    /// Opens the package's website, or with `page` its page on the AUR or
+    /// archlinux.org, in the browser.
+    fn open_link(&mut self, page: bool) {
+        let Some(details) = &self.details else {
+            return;
+        };
+        let url = if page {
+            self.details_for
+                .as_ref()
+                .and_then(|id| details.package_page(id))
+        } else {
+            details.url.clone()
+        };
+        match url {
+            Some(url) => {
+                if let Err(e) = open_url(&url) {
+                    self.messages = vec![format!("Failed to open {}: {}", url, e)];
+                }
             }
+            None => self.messages = vec![format!("No web page known for {}", details.name)],
+        }
+    }
 
-            terminal.draw(|frame| draw_ui(frame, &mut self))?;
-
-            if event::poll(std::time::Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    match self.input_mode {
-                        InputMode::Normal if key.kind == KeyEventKind::Press => match key.code {
-                            KeyCode::Char('i') => {
-                                let _ = self.run_command(terminal);
-                            }
-                            KeyCode::Char(' ') => {
-                                if !self.packages.is_empty() {
-                                    let pkg = &self.packages[self.selected];
-                                    let name = pkg.name.clone();
-
-                                    let is_checked = !self.checked[self.selected];
-                                    self.checked[self.selected] = is_checked;
-
-                                    if is_checked {
-                                        self.selected_names.insert(name);
-                                    } else {
-                                        self.selected_names.remove(&name);
-                                    }
-                                }
-                            }
-                            KeyCode::Char('e') => self.input_mode = InputMode::Editing,
-                            KeyCode::Char('q') => return Ok(()),
-
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                if self.selected > 0 {
-                                    self.selected -= 1;
-                                    self.list_state.select(Some(self.selected));
-                                }
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                if self.selected + 1 < self.packages.len() {
-                                    self.selected += 1;
-                                    self.list_state.select(Some(self.selected));
-                                }
-                            }
-                            _ => {}
-                        },
-
-                        InputMode::Editing if key.kind == KeyEventKind::Press => match key.code {
-                            KeyCode::Enter => {
-                                self.input_mode = InputMode::Normal;
-                                self.pending_search = true;
-                                self.last_input_time = Instant::now();
-                            }
-                            KeyCode::Char(c) => self.enter_char(c),
-                            KeyCode::Backspace => self.delete_char(),
-                            KeyCode::Left => self.move_cursor_left(),
-                            KeyCode::Right => self.move_cursor_right(),
-                            KeyCode::Esc => self.input_mode = InputMode::Normal,
-                            _ => {}
-                        },
-
-                        _ => {}
+    /// Opens the Files tab on the package whose details are shown.
+    fn open_files(&mut self) {
+        let Some(id) = self.details_for.clone() else {
+            return;
+        };
+        let installed = self
+            .packages
+            .get(self.selected)
+            .is_some_and(|p| p.id == id && p.installed);
+
+        let tx = self.tx.clone();
+        let task = tokio::spawn({
+            let id = id.clone();
+            async move {
+                let files = files::file_list(&id, installed).await;
+                let _ = tx.send(Message::FilesLoaded { id, files });
+            }
+        });
+        self.files_task = Some(task.abort_handle());
+        self.files = Some(FilesView::new(id));
+    }
+
+    /// Back to the details tab, cancelling a listing still running.
+    fn close_files(&mut self) {
+        if let Some(task) = self.files_task.take() {
+            task.abort();
+        }
+        self.files = None;
+    }
+
+    fn handle_files_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.files.as_mut() else {
+            return;
+        };
+
+        if view.filtering {
+            match key.code {
+                KeyCode::Char(c) => view.filter.push(c),
+                KeyCode::Backspace => {
+                    view.filter.pop();
+                }
+                KeyCode::Esc => {
+                    view.filter.clear();
+                    view.filtering = false;
+                }
+                KeyCode::Enter => view.filtering = false,
+                _ => {}
+            }
+            view.selected = 0;
+            return;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => view.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => view.move_by(1),
+            KeyCode::PageUp => view.move_by(-10),
+            KeyCode::PageDown => view.move_by(10),
+            KeyCode::Enter | KeyCode::Char(' ') => view.toggle_selected(),
+            KeyCode::Char('/') => view.filtering = true,
+            KeyCode::Esc | KeyCode::Char('f') => self.close_files(),
+            KeyCode::Char('q') => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Switches to looking up which packages own a path or command.
+    fn open_owners(&mut self) {
+        self.view = View::Owners;
+        self.input.clear();
+        self.character_index = 0;
+        self.set_packages(Vec::new());
+        self.messages = vec!["Type a path or command and press Enter".to_string()];
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Looks up the owners of the typed path or command off the event loop.
+    fn find_owners(&mut self) {
+        let target = self.input.trim().to_string();
+        if target.is_empty() {
+            return;
+        }
+        self.loading = true;
+        self.messages = vec![format!("Looking up {}...", target)];
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let packages = files::find_owners(&target).await;
+            let _ = tx.send(Message::OwnersFound { target, packages });
+        });
+    }
+
+    /// Package whose details the pane shows when row `index` is selected.
+    fn details_id(&self, index: usize) -> Option<PackageId> {
+        match self.view {
+            View::Search | View::Orphans | View::Leaves | View::Owners => {
+                self.packages.get(index).map(|p| p.id.clone())
+            }
+            View::History if self.history_source == HistorySource::System => self
+                .history_filtered
+                .get(index)
+                .map(|&i| &self.system_log[i])
+                .map(|e| PackageId::new("local", "local", &e.package, &e.version)),
+            _ => None,
+        }
+    }
+
+    /// Shows the selected package's details once the selection has moved,
+    /// loading them on a task unless cached. The rows either side are
+    /// prefetched, and lookups for rows left behind are cancelled.
+    fn request_details(&mut self) {
+        let target = self.details_id(self.selected);
+        if target == self.details_for {
+            return;
+        }
+        self.details = target.as_ref().and_then(managers::cached_details);
+        self.details_scroll = 0;
+        self.details_loading = target.is_some() && self.details.is_none();
+        self.details_for = target;
+
+        let wanted: Vec<PackageId> = [
+            Some(self.selected),
+            self.selected.checked_sub(1),
+            Some(self.selected + 1),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|index| self.details_id(index))
+        .filter(|id| managers::cached_details(id).is_none())
+        .collect();
+
+        self.details_tasks.retain(|id, task| {
+            let keep = wanted.contains(id);
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+
+        for id in wanted {
+            if self.details_tasks.contains_key(&id) {
+                continue;
+            }
+            let tx = self.tx.clone();
+            let lookup = self.load_details(&id);
+            let task = tokio::spawn({
+                let id = id.clone();
+                async move {
+                    let details = lookup.await.map(Box::new);
+                    let _ = tx.send(Message::DetailsLoaded { id, details });
+                }
+            });
+            self.details_tasks.insert(id, task.abort_handle());
+        }
+    }
+
+    /// Looks up the details of `id` through its backend. Installed packages
+    /// come from the local database, falling back to the sync databases once
+    /// they have been removed.
+    fn load_details(&self, id: &PackageId) -> BoxFuture<'static, Option<PackageDetails>> {
+        if let Some(backend) = self.backend(&id.provider) {
+            return backend.details(id);
+        }
+        let id = id.clone();
+        Box::pin(async move {
+            if let Some(details) = managers::details_package(&id).await {
+                return Some(details);
+            }
+            if id.provider != "local" {
+                return None;
+            }
+            let sync = PackageId::new("pacman", "", &id.name, &id.version);
+            managers::details_package(&sync).await
+        })
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match self.input_mode {
+            _ if self.job_visible && key.kind == KeyEventKind::Press => self.handle_job_key(key),
+            InputMode::Normal if self.review.is_some() && key.kind == KeyEventKind::Press => {
+                self.handle_review_key(key)
+            }
+            InputMode::Normal if self.files.is_some() && key.kind == KeyEventKind::Press => {
+                self.handle_files_key(key)
+            }
+            InputMode::Normal if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('y') if self.confirm.is_some() => {
+                    match self.confirm.take() {
+                        Some(Confirm::Install) => self.run_command(),
+                        Some(Confirm::Remove) => self.remove_basket(),
+                        None => {}
+                    }
+                    if self.view == View::Basket {
+                        self.open_basket();
+                    }
+                }
+                _ if self.confirm.is_some() => self.confirm = None,
+                _ if key.code != KeyCode::Char('y')
+                    && self.cache.as_ref().is_some_and(|c| c.confirm) =>
+                {
+                    if let Some(view) = self.cache.as_mut() {
+                        view.confirm = false;
+                    }
+                }
+                KeyCode::Char('i') if matches!(self.view, View::Search | View::Basket) => {
+                    self.request_confirm(Confirm::Install)
+                }
+                KeyCode::Char('i') if self.view == View::Manifest => self.install_missing(),
+                KeyCode::Char('c') if matches!(self.view, View::Orphans | View::Leaves) => {
+                    self.cleanup()
+                }
+                KeyCode::Enter if self.view == View::History => self.rerun_history(false),
+                KeyCode::Tab if self.view == View::Search => self.toggle_expanded(),
+                KeyCode::Tab if self.view == View::History => {
+                    self.history_source = match self.history_source {
+                        HistorySource::Trx => HistorySource::System,
+                        HistorySource::System => HistorySource::Trx,
+                    };
+                    self.filter_history();
+                }
+                KeyCode::Char('z') if self.view == View::History => self.rerun_history(true),
+                KeyCode::Char('r') if matches!(self.view, View::Search | View::Basket) => {
+                    self.request_confirm(Confirm::Remove)
+                }
+                KeyCode::Char('b') => self.open_basket(),
+                KeyCode::Char(' ') | KeyCode::Char('x') | KeyCode::Delete
+                    if self.view == View::Basket =>
+                {
+                    self.remove_from_basket()
+                }
+                KeyCode::Char('u') => {
+                    self.queue_job(|| managers::system_upgrade().map(Some));
+                }
+                KeyCode::Char('U') => self.upgrade_aur(),
+                KeyCode::Char('p') if self.job.is_some() => self.job_visible = true,
+                KeyCode::Char('o') => self.open_analysis(View::Orphans),
+                KeyCode::Char('l') => self.open_analysis(View::Leaves),
+                KeyCode::Char('h') => self.open_history(),
+                KeyCode::Esc if self.view != View::Search => self.leave_view(),
+                KeyCode::Char('d') if self.view == View::Search => self.open_downgrade(),
+                KeyCode::Char('C') => self.open_cache(),
+                KeyCode::Char('+') if self.view == View::Cache => {
+                    self.update_policy(|p| p.keep += 1)
+                }
+                KeyCode::Char('-') if self.view == View::Cache => {
+                    self.update_policy(|p| p.keep = p.keep.saturating_sub(1))
+                }
+                KeyCode::Char('n') if self.view == View::Cache => {
+                    self.update_policy(|p| p.remove_uninstalled = !p.remove_uninstalled)
+                }
+                KeyCode::Char('x') if self.view == View::Cache => self.clean_cache(false),
+                KeyCode::Char('y') if self.cache.as_ref().is_some_and(|c| c.confirm) => {
+                    self.clean_cache(true)
+                }
+                KeyCode::Enter if self.view == View::Downgrade => self.install_cached_version(),
+                KeyCode::Char(' ')
+                    if matches!(self.view, View::Search | View::Orphans | View::Leaves)
+                        && !self.packages.is_empty() =>
+                {
+                    let pkg = self.packages[self.selected].clone();
+                    self.toggle_basket(pkg);
+                    self.update_impact();
+                }
+                KeyCode::Char('e') => {
+                    if matches!(
+                        self.view,
+                        View::Orphans | View::Leaves | View::Downgrade | View::Basket
+                    ) {
+                        self.leave_view();
                     }
+                    self.input_mode = InputMode::Editing;
+                }
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Char('f') if self.details_for.is_some() => self.open_files(),
+                KeyCode::Char('O') => self.open_owners(),
+                KeyCode::Char('w') => self.open_link(false),
+                KeyCode::Char('W') => self.open_link(true),
+                KeyCode::PageDown => self.details_scroll = self.details_scroll.saturating_add(10),
+                KeyCode::PageUp => self.details_scroll = self.details_scroll.saturating_sub(10),
+                KeyCode::Char('J') => self.details_scroll = self.details_scroll.saturating_add(1),
+                KeyCode::Char('K') => self.details_scroll = self.details_scroll.saturating_sub(1),
+
+                KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => {
+                    self.selected -= 1;
+                    self.list_state.select(Some(self.selected));
+                    self.update_impact();
+                }
+                KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.list_len() => {
+                    self.selected += 1;
+                    self.list_state.select(Some(self.selected));
+                    self.update_impact();
+                }
+                _ => {}
+            },
+
+            InputMode::Editing if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => {
+                    self.input_mode = InputMode::Normal;
+                    self.pending_search = true;
+                    self.last_input_time = Instant::now();
                 }
+                KeyCode::Char(c) => self.enter_char(c),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Esc => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+
+            _ => {}
+        }
+    }
+}
+
+/// Opens `url` with `xdg-open` without waiting for the browser.
+fn open_url(url: &str) -> std::io::Result<()> {
+    let mut child = tokio::process::Command::new("xdg-open")
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reaped in the background so it doesn't linger as a zombie.
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(())
+}
+
+/// Runs `input` against every backend it allows, falling back to the full
+/// package index when their own search finds nothing, and returns the
+/// ranked results grouped by name.
+async fn search(
+    input: &str,
+    backends: Vec<Arc<dyn Backend>>,
+    matcher: Arc<Mutex<IncrementalMatcher>>,
+) -> Vec<Vec<Package>> {
+    // Full-index matches worth checking against the whole query.
+    const INDEX_LIMIT: usize = 200;
+
+    let query = Query::parse(input);
+    let words = query.search_words();
+
+    let searches = backends
+        .iter()
+        .filter(|backend| query.allows_provider(backend.name()))
+        .map(|backend| {
+            let backend = Arc::clone(backend);
+            let words = words.clone();
+            tokio::task::spawn_blocking(move || backend.search(&words))
+        });
+    let found: Vec<Package> = join_all(searches)
+        .await
+        .into_iter()
+        .flat_map(Result::unwrap_or_default)
+        .collect();
+
+    let ranked = tokio::task::spawn_blocking(move || {
+        let mut matched: Vec<Package> = found
+            .into_iter()
+            .filter_map(|mut pkg| query.apply(&mut pkg).then_some(pkg))
+            .collect();
+
+        // The backends' own search found nothing, e.g. for `nvm`: fuzzy-match
+        // every package name they know about instead, then allow typos if
+        // that still finds nothing.
+        if matched.is_empty() && !words.is_empty() {
+            let indexes: Vec<_> = backends
+                .iter()
+                .filter(|backend| query.allows_provider(backend.name()))
+                .map(|backend| package_index(backend.as_ref()))
+                .collect();
+            let candidates: Vec<&Package> = indexes.iter().flat_map(|index| index.iter()).collect();
+
+            if let Some(term) = query.name_term() {
+                let names: Vec<&str> = candidates.iter().map(|p| p.id.name.as_str()).collect();
+                // The indexes last for the session, so the backends searched
+                // identify the list.
+                let list = backends
+                    .iter()
+                    .filter(|backend| query.allows_provider(backend.name()))
+                    .map(|backend| backend.name())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let hits = matcher
+                    .lock()
+                    .unwrap()
+                    .search(&list, term, &names, INDEX_LIMIT);
+                matched = hits
+                    .into_iter()
+                    .filter_map(|(i, _)| {
+                        let mut pkg = candidates[i].clone();
+                        query.apply(&mut pkg).then_some(pkg)
+                    })
+                    .collect();
+            }
+
+            if matched.is_empty() {
+                matched = candidates
+                    .into_iter()
+                    .filter_map(|pkg| {
+                        let mut pkg = pkg.clone();
+                        query.apply_tolerant(&mut pkg).then_some(pkg)
+                    })
+                    .collect();
+            }
+        }
+
+        ranking::rank_all(&mut matched, &Usage::load(), &config::get().ranking);
+        let mut all = managers::merge_sources(matched, config::get());
+        all.truncate(50);
+        all
+    });
+    ranked.await.unwrap_or_default()
+}
+
+/// Groups packages by provider, keeping the order providers first appear in.
+fn group_by_provider(packages: &[Package]) -> Vec<(String, HashSet<String>)> {
+    let mut groups: Vec<(String, HashSet<String>)> = Vec::new();
+    for pkg in packages {
+        match groups
+            .iter_mut()
+            .find(|(provider, _)| *provider == pkg.id.provider)
+        {
+            Some((_, names)) => {
+                names.insert(pkg.id.name.clone());
             }
+            None => groups.push((
+                pkg.id.provider.clone(),
+                HashSet::from([pkg.id.name.clone()]),
+            )),
         }
     }
+    groups
 }
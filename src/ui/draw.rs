@@ -1,21 +1,52 @@
 use ratatui::{
     Frame,
-    layout::{Constraint, Layout, Position},
+    layout::{Constraint, Layout, Position, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
 
-use crate::ui::{app::App, input::InputMode};
+use crate::ui::{
+    app::{App, Confirm, FilesView, HistorySource, ReviewView, View},
+    highlight,
+    input::InputMode,
+    pty::JobStatus,
+    text,
+};
 use textwrap::wrap;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::history::pacman_log::LogAction;
+use crate::managers::details::PackageDetails;
+use crate::managers::review::Changes;
+use crate::managers::{Package, PackageId, cache};
+use crate::ranking::Rank;
+/// Splits the screen into the main area and the job pane below it.
+fn split_job_pane(area: Rect, job_visible: bool) -> [Rect; 2] {
+    let job_height = if job_visible {
+        Constraint::Percentage(50)
+    } else {
+        Constraint::Length(0)
+    };
+    Layout::vertical([Constraint::Min(1), job_height]).areas(area)
+}
+
+/// Splits the main area into the package list and the details pane.
+fn split_columns(area: Rect, job_visible: bool) -> [Rect; 2] {
+    let [main_area, _] = split_job_pane(area, job_visible);
+    Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main_area)
+}
 
-use crate::managers::details_package;
-/// draw_ui updated to accept a mutable App reference so it can use App.list_state.
-/// The important change: use render_stateful_widget with app.list_state so ratatui keeps the
-/// selected item visible (scrolls) and can apply highlight styling.
-pub fn draw_ui(frame: &mut Frame, app: &mut App) {
-    let horizontal = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]);
-    let [search_area, details_area] = horizontal.areas(frame.area());
+/// Size of the terminal inside the job pane for a screen of `area`.
+pub fn job_pane_inner(area: Rect) -> Rect {
+    Block::bordered().inner(split_job_pane(area, true)[1])
+}
+
+/// Draws the whole UI from `app`. `list_state` is passed separately because
+/// ratatui updates its scroll offset so the selected item stays visible.
+pub fn draw_ui(frame: &mut Frame, app: &App, list_state: &mut ListState) {
+    let [_, job_area] = split_job_pane(frame.area(), app.job_visible);
+    let [search_area, details_area] = split_columns(frame.area(), app.job_visible);
 
     let vertical = Layout::vertical([
         Constraint::Length(1),
@@ -24,14 +55,26 @@ pub fn draw_ui(frame: &mut Frame, app: &mut App) {
     ]);
     let [help_area, input_area, list_area] = vertical.areas(search_area);
 
-    let (help_lines, style) = match app.input_mode {
+    let (mut help_lines, style) = match app.input_mode {
         InputMode::Normal => (
             vec![
                 "Press ".into(),
                 "q".bold(),
                 " to quit, ".into(),
                 "e".bold(),
-                " to edit".into(),
+                " to edit, ".into(),
+                "o".bold(),
+                "/".into(),
+                "l".bold(),
+                " orphans/leaves, ".into(),
+                "h".bold(),
+                " history, ".into(),
+                "d".bold(),
+                " downgrade, ".into(),
+                "O".bold(),
+                " owners, ".into(),
+                "b".bold(),
+                format!(" basket ({})", app.basket.len()).into(),
             ],
             Style::default().add_modifier(Modifier::RAPID_BLINK),
         ),
@@ -47,6 +90,13 @@ pub fn draw_ui(frame: &mut Frame, app: &mut App) {
         ),
     };
 
+    if let Some(job) = app.job.as_ref().filter(|_| !app.job_visible) {
+        let state = if job.is_running() { "running" } else { "done" };
+        help_lines.push(format!(" | job {} (", state).into());
+        help_lines.push("p".bold());
+        help_lines.push(" to show)".into());
+    }
+
     let text = Text::from(Line::from(help_lines)).patch_style(style);
     frame.render_widget(Paragraph::new(text), help_area);
 
@@ -60,7 +110,17 @@ pub fn draw_ui(frame: &mut Frame, app: &mut App) {
 
     // Build items (use packages if available; otherwise, fallback to messages)
 
-    let items: Vec<ListItem> = if app.packages.is_empty() {
+    let items: Vec<ListItem> = if app.view == View::History {
+        history_items(app)
+    } else if app.view == View::Downgrade {
+        downgrade_items(app)
+    } else if app.view == View::Cache {
+        cache_items(app)
+    } else if app.view == View::Manifest {
+        manifest_items(app)
+    } else if app.view == View::Basket {
+        basket_items(app)
+    } else if app.packages.is_empty() {
         app.messages
             .iter()
             .enumerate()
@@ -70,104 +130,1107 @@ pub fn draw_ui(frame: &mut Frame, app: &mut App) {
         app.packages
             .iter()
             .enumerate()
-            .map(|(_i, p)| {
-                // package name and provider
-                let package: Vec<&str> = p.name.split('/').collect();
-                let pkg_name = if package.get(1).unwrap_or(&"").len() > 24 {
-                    format!("{}...", &package.get(1).unwrap_or(&"")[..22])
+            .map(|(i, p)| {
+                // Further sources of the package above are grouped under it.
+                let grouped = i > 0 && app.packages[i - 1].id.name == p.id.name;
+                let name_spans = if grouped {
+                    vec![Span::raw(text::pad(&format!("  └ {}", p.id.repo), 28))]
                 } else {
-                    package.get(1).unwrap_or(&"").to_string()
+                    highlighted_name(&p.id.name, &p.matched, 28)
                 };
-                let provider = package.get(0).unwrap_or(&"");
 
-                // version formatting
-                let version = if p.version.len() > 12 {
-                    format!("{}...", &p.version[..8])
-                } else {
-                    p.version.clone()
-                };
-
-                let checked_symbol = if app.selected_names.contains(&p.name) {
-                    "[*]"
-                } else {
-                    "[ ]"
-                };
+                let checked_symbol = if app.in_basket(p) { "[*]" } else { "[ ]" };
 
-                let content = Span::raw(format!(
-                    "{} {: <28} {: <20} {}",
-                    checked_symbol, pkg_name, version, provider
-                ));
+                let mut spans = vec![Span::raw(format!("{} ", checked_symbol))];
+                spans.extend(name_spans);
+                spans.push(Span::raw(format!(
+                    " {} {}",
+                    text::pad(&p.id.version, 20),
+                    p.id.repo
+                )));
 
-                ListItem::new(Line::from(content))
+                if app.view == View::Owners {
+                    spans.push(Span::styled(
+                        format!(" {}", p.description),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                if p.installed && matches!(app.view, View::Search | View::Owners) {
+                    spans.push(Span::styled(
+                        " [installed]",
+                        Style::default().fg(Color::Green),
+                    ));
+                }
+                let hidden = app.hidden_sources(p);
+                if hidden > 0 {
+                    spans.push(Span::styled(
+                        format!(" +{}", hidden),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect()
     };
 
+    let list_title = match app.view {
+        View::Search => "Packages (Tab: other sources)",
+        View::Orphans => "Orphans (c: remove all or checked)",
+        View::Leaves => "Leaves (c: remove checked)",
+        View::History => match app.history_source {
+            HistorySource::Trx => "History: trx (Tab: pacman.log, Enter: re-run, z: undo)",
+            HistorySource::System => "History: pacman.log (Tab: trx, e: filter)",
+        },
+        View::Downgrade => "Cached versions (Enter: install, Esc: back)",
+        View::Cache => "Package cache (+/-: keep, n: uninstalled, x: clean)",
+        View::Manifest => "Manifest diff (i: install missing, Esc: back)",
+        View::Basket => "Basket (x: drop, i: install all, r: remove all, Esc: back)",
+        View::Owners => "Owners (e: path or command, Esc: back)",
+    };
+
     // Create a List with a highlight style and symbol
     let list = List::new(items)
-        .block(Block::bordered().title("Packages"))
+        .block(Block::bordered().title(list_title))
         .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
         .highlight_symbol("» ");
 
-    frame.render_stateful_widget(list, list_area, &mut app.list_state);
+    frame.render_stateful_widget(list, list_area, list_state);
+    if let Some(files) = &app.files {
+        draw_files(frame, files, details_area);
+    } else {
+        let title = if app.details.is_some() {
+            "Details (PgUp/PgDn: scroll, w: website, W: package page, f: files)"
+        } else {
+            "Details"
+        };
+        frame.render_widget(
+            details_paragraph(app, Block::bordered().inner(details_area).width)
+                .scroll((app.details_scroll, 0))
+                .block(Block::bordered().title(title)),
+            details_area,
+        );
+    }
+
+    if let InputMode::Editing = app.input_mode {
+        // Wide characters take two cells, so the cursor goes by display width.
+        let before: String = app.input.chars().take(app.character_index).collect();
+        frame.set_cursor_position(Position::new(
+            input_area.x + text::width(&before) as u16 + 1,
+            input_area.y + 1,
+        ));
+    }
+
+    if app.job_visible {
+        draw_job_pane(frame, app, job_area);
+    }
+
+    if let Some(view) = &app.review {
+        draw_review(
+            frame,
+            view,
+            split_job_pane(frame.area(), app.job_visible)[0],
+        );
+    }
+
+    if let Some(action) = app.confirm {
+        draw_confirm(frame, app, action);
+    }
+}
+
+/// The details pane's contents for a pane `width` columns wide inside its border.
+fn details_paragraph(app: &App, width: u16) -> Paragraph<'static> {
     let mut details_lines: Vec<Line> = Vec::new();
 
-    if app.packages.is_empty() {
+    if app.view == View::History {
+        details_lines = history_details(app, width);
+    } else if app.view == View::Downgrade {
+        details_lines = downgrade_details(app);
+    } else if app.view == View::Cache {
+        details_lines = cache_details(app);
+    } else if app.view == View::Manifest {
+        details_lines = manifest_details(app);
+    } else if app.view == View::Basket {
+        details_lines = basket_details(app);
+    } else if app.packages.is_empty() {
         details_lines.push(Line::from("No package selected"));
     } else {
-        if app.selected != app.last_selected {
-            let pkg_name = &app.packages[app.selected].name;
-            let provider = &app.packages[app.selected].provider;
-            app.details = details_package(pkg_name, provider);
-            app.last_selected = app.selected;
+        if let Some(ref details) = app.details {
+            details_lines.extend(package_details_lines(details, app.details_target(), width));
+        } else if app.details_loading {
+            details_lines.extend(loading_placeholder(&app.packages[app.selected]));
+        } else {
+            details_lines.push(Line::from("No details available"));
         }
 
-        if let Some(ref info) = app.details {
-            let mut sorted: Vec<_> = info.iter().collect();
-            sorted.sort_by_key(|(k, _)| *k);
+        if let Some(rank) = &app.packages[app.selected].rank {
+            details_lines.extend(rank_lines(rank));
+        }
 
-            let key_width = 15; // fixed width for keys
+        if let Some(ref impact) = app.impact {
+            let heading = Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD);
 
-            for (key, value) in sorted {
-                let key_text = format!("{:<key_width$}: ", key, key_width = key_width);
-                let indent = " ".repeat(key_text.len());
+            details_lines.push(Line::from(""));
+            details_lines.push(Line::from(Span::styled("Removal impact", heading)));
 
-                let value_wrapped = wrap(value, 80 - key_text.len());
+            let none = "None".to_string();
+            for (label, names) in [
+                ("Would break   : ", &impact.breaks),
+                ("Also orphaned : ", &impact.orphaned),
+            ] {
+                let value = if names.is_empty() {
+                    none.clone()
+                } else {
+                    names.join(" ")
+                };
+                let indent = " ".repeat(label.len());
 
-                if let Some(first) = value_wrapped.get(0) {
-                    details_lines.push(Line::from(vec![
-                        Span::styled(
-                            key_text.clone(),
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
-                        ),
-                        Span::raw(first.to_string()),
-                    ]));
+                for (i, line) in wrap(&value, value_width(width, label.len()))
+                    .iter()
+                    .enumerate()
+                {
+                    if i == 0 {
+                        details_lines.push(Line::from(vec![
+                            Span::styled(label, heading),
+                            Span::raw(line.to_string()),
+                        ]));
+                    } else {
+                        details_lines.push(Line::from(format!("{}{}", indent, line)));
+                    }
                 }
+            }
+        }
+    }
+
+    Paragraph::new(details_lines).wrap(Wrap { trim: false })
+}
+
+/// How far the details pane on a screen of `area` can scroll before its
+/// last line would leave the bottom of the pane.
+pub fn details_scroll_limit(app: &App, area: Rect) -> u16 {
+    let inner = Block::bordered().inner(split_columns(area, app.job_visible)[1]);
+    let lines = details_paragraph(app, inner.width).line_count(inner.width);
+    u16::try_from(lines)
+        .unwrap_or(u16::MAX)
+        .saturating_sub(inner.height)
+}
+
+/// Columns left for a value after a `key_width` wide label, never so few
+/// that every word lands on its own line.
+fn value_width(width: u16, key_width: usize) -> usize {
+    (width as usize).saturating_sub(key_width).max(20)
+}
+
+/// `details` of the package `id` under a name, version and source header,
+/// then its sections: a heading, then one `key: value` row per field,
+/// wrapped to `width` with the continuation lines indented under the value.
+fn package_details_lines(
+    details: &PackageDetails,
+    id: Option<&PackageId>,
+    width: u16,
+) -> Vec<Line<'static>> {
+    let heading = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let key_style = Style::default().fg(Color::Yellow);
+
+    let mut header = vec![
+        Span::styled(
+            details.name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(" {}", details.version),
+            Style::default().fg(Color::Green),
+        ),
+    ];
+    if let Some(id) = id {
+        let source = if id.repo.is_empty() || id.repo == id.provider {
+            id.provider.clone()
+        } else {
+            format!("{} via {}", id.repo, id.provider)
+        };
+        header.push(Span::styled(
+            format!("  {}", source),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let mut lines = vec![Line::from(header)];
 
-                // Remaining lines => indent + rest of value
-                for line in value_wrapped.iter().skip(1) {
-                    details_lines.push(Line::from(format!("{}{}", indent, line)));
+    for section in details.sections() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(section.title, heading)));
+
+        for (key, values) in section.fields {
+            let key_text = format!("{:<15}: ", key);
+            let indent = " ".repeat(key_text.len());
+            let wrapped = values
+                .iter()
+                .flat_map(|value| wrap(value, value_width(width, key_text.len())))
+                .map(|line| line.into_owned());
+
+            for (i, line) in wrapped.enumerate() {
+                if i == 0 {
+                    lines.push(Line::from(vec![
+                        Span::styled(key_text.clone(), key_style),
+                        Span::raw(line),
+                    ]));
+                } else {
+                    lines.push(Line::from(format!("{}{}", indent, line)));
                 }
             }
-        } else {
-            details_lines.push(Line::from("Loading details..."));
         }
     }
+    lines
+}
+
+/// The Files tab: the package's file tree, narrowed by the filter typed
+/// after `/`, with the cursor kept in view.
+fn draw_files(frame: &mut Frame, files: &FilesView, area: Rect) {
+    let mut block = Block::bordered().title(format!(
+        "Files of {} (Enter: fold, /: filter, Esc: details)",
+        files.id.name
+    ));
+    if files.filtering || !files.filter.is_empty() {
+        block = block.title_bottom(format!("/{}", files.filter));
+    }
+
+    let rows = files.rows();
+    let message = match &files.paths {
+        None => Some("Loading file list...".to_string()),
+        Some(Err(e)) => Some(e.clone()),
+        Some(Ok(_)) if rows.is_empty() && files.filter.is_empty() => Some("No files".to_string()),
+        Some(Ok(_)) if rows.is_empty() => Some("No files match".to_string()),
+        Some(Ok(_)) => None,
+    };
+    if let Some(message) = message {
+        frame.render_widget(Paragraph::new(message).block(block), area);
+        return;
+    }
+
+    let inner_width = block.inner(area).width as usize;
+    let dir_style = Style::default()
+        .fg(Color::Blue)
+        .add_modifier(Modifier::BOLD);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let marker = match (row.is_dir, row.collapsed) {
+                (true, true) => "▸ ",
+                (true, false) => "▾ ",
+                (false, _) => "  ",
+            };
+            let indent = format!("{}{}", "  ".repeat(row.depth), marker);
+            let width = inner_width.saturating_sub(indent.len()).max(8);
+
+            let mut spans = vec![Span::raw(indent)];
+            let name = highlighted_name(row.name, &row.matched, width);
+            if row.is_dir {
+                spans.extend(name.into_iter().map(|span| span.patch_style(dir_style)));
+            } else {
+                spans.extend(name);
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    // A fresh state scrolls just far enough to show the cursor.
+    let mut state = ListState::default().with_selected(Some(files.selected));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(block.title(
+                Line::from(format!("{}/{}", files.selected + 1, rows.len())).right_aligned(),
+            ))
+            .highlight_style(Style::default().bg(Color::Blue).fg(Color::White)),
+        area,
+        &mut state,
+    );
+}
+
+/// What the search result already tells about `pkg`, shown until its
+/// `-Si` details arrive.
+fn loading_placeholder(pkg: &Package) -> Vec<Line<'static>> {
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut lines: Vec<Line> = [
+        ("Name", pkg.id.name.clone()),
+        ("Version", pkg.id.version.clone()),
+        ("Repository", pkg.id.repo.clone()),
+        ("Description", pkg.description.clone()),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| {
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", key), key_style),
+            Span::raw(value),
+        ])
+    })
+    .collect();
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Loading details...",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines
+}
+
+/// The build files of the package under review, what changed since the
+/// last review, and the latest AUR comments.
+fn review_lines(view: &ReviewView) -> Vec<Line<'static>> {
+    let heading = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let name = view.pending.front().cloned().unwrap_or_default();
+    let short = |revision: &str| revision.chars().take(10).collect::<String>();
+
+    let review = match &view.current {
+        None => {
+            return vec![Line::from(format!(
+                "Fetching the build files of {} from the AUR...",
+                name
+            ))];
+        }
+        Some(Err(e)) => {
+            return vec![
+                Line::styled(
+                    format!("Couldn't fetch the build files of {}: {}", name, e),
+                    Style::default().fg(Color::Red),
+                ),
+                Line::from(""),
+                Line::from("It can't be approved unreviewed; skip it or cancel the install."),
+            ];
+        }
+        Some(Ok(review)) => review,
+    };
+
+    let mut header = vec![Span::styled(
+        review.name.clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if review.base != review.name {
+        header.push(Span::raw(format!("  base {}", review.base)));
+    }
+    header.push(Span::styled(
+        format!("  revision {}", short(&review.revision)),
+        Style::default().fg(Color::DarkGray),
+    ));
+    let mut lines = vec![Line::from(header)];
+    if !review.depends.is_empty() {
+        lines.push(Line::from(format!(
+            "Needs from the AUR, reviewed next and built first: {}",
+            review.depends.join(", ")
+        )));
+    }
 
-    // Now render
+    match &review.changes {
+        Changes::New => lines.push(Line::styled(
+            "Never reviewed before",
+            Style::default().fg(Color::Yellow),
+        )),
+        Changes::Unchanged => lines.push(Line::styled(
+            "Unchanged since your last review",
+            Style::default().fg(Color::Green),
+        )),
+        Changes::Diff(diff) => {
+            lines.push(Line::styled(
+                "Changed since your last review:",
+                Style::default().fg(Color::Yellow),
+            ));
+            lines.push(Line::from(""));
+            lines.extend(highlight::diff(diff));
+        }
+        Changes::Unknown(reviewed) => lines.push(Line::styled(
+            format!(
+                "The reviewed revision {} is gone from the AUR history; read everything again",
+                short(reviewed)
+            ),
+            Style::default().fg(Color::Red),
+        )),
+    }
+
+    for (file, contents) in &review.files {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(file.clone(), heading));
+        lines.extend(highlight::shell(contents));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled("Latest comments", heading));
+    match &review.comments {
+        Ok(comments) if comments.is_empty() => lines.push(Line::from("None")),
+        Ok(comments) => {
+            for comment in comments {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        comment.author.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("  {}", comment.date),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]));
+                lines.extend(
+                    comment
+                        .text
+                        .lines()
+                        .map(|line| Line::from(format!("  {}", line))),
+                );
+                lines.push(Line::from(""));
+            }
+        }
+        Err(e) => lines.push(Line::styled(
+            format!("Unavailable: {}", e),
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+    lines
+}
+
+fn review_block(view: &ReviewView) -> Block<'static> {
+    let name = view.pending.front().cloned().unwrap_or_default();
+    let mut title = format!("Review {} before building", name);
+    if view.pending.len() > 1 {
+        title.push_str(&format!(" ({} more queued)", view.pending.len() - 1));
+    }
+    Block::bordered()
+        .title(title)
+        .title_bottom("a: approve, s: skip, Esc: cancel install, j/k/PgUp/PgDn: scroll")
+        .border_style(Style::default().fg(Color::Yellow))
+}
+
+/// How far the review on a screen of `area` can scroll before its last
+/// line would leave the bottom of the pane.
+pub fn review_scroll_limit(view: &ReviewView, area: Rect, job_visible: bool) -> u16 {
+    let inner = review_block(view).inner(split_job_pane(area, job_visible)[0]);
+    let lines = Paragraph::new(review_lines(view))
+        .wrap(Wrap { trim: false })
+        .line_count(inner.width);
+    u16::try_from(lines)
+        .unwrap_or(u16::MAX)
+        .saturating_sub(inner.height)
+}
+
+/// The review covering the main area; the job pane below stays visible.
+fn draw_review(frame: &mut Frame, view: &ReviewView, area: Rect) {
+    frame.render_widget(Clear, area);
     frame.render_widget(
-        Paragraph::new(details_lines)
+        Paragraph::new(review_lines(view))
             .wrap(Wrap { trim: false })
-            .block(Block::bordered().title("Details")),
-        details_area,
+            .scroll((view.scroll, 0))
+            .block(review_block(view)),
+        area,
     );
+}
 
-    if let InputMode::Editing = app.input_mode {
-        frame.set_cursor_position(Position::new(
-            input_area.x + app.character_index as u16 + 1,
-            input_area.y + 1,
+/// Centered prompt listing what a basket operation is about to touch.
+fn draw_confirm(frame: &mut Frame, app: &App, action: Confirm) {
+    let verb = match action {
+        Confirm::Install => "Install",
+        Confirm::Remove => "Remove",
+    };
+
+    // Installs skip what no backend can install; removals take everything.
+    let (listed, skipped): (Vec<&Package>, Vec<&Package>) = app
+        .basket
+        .iter()
+        .partition(|pkg| action == Confirm::Remove || app.installable(pkg));
+
+    let mut lines = vec![Line::from(format!("{} {} packages?", verb, listed.len()))];
+    lines.push(Line::from(""));
+    for pkg in listed {
+        lines.push(Line::from(format!("  {} ({})", pkg.id, pkg.id.provider)));
+    }
+    if !skipped.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "Skipped, already installed and not from a search:",
+            Style::default().fg(Color::DarkGray),
         ));
+        for pkg in skipped {
+            lines.push(Line::styled(
+                format!("  {}", pkg.id),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
     }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        "Press ".into(),
+        "y".bold(),
+        " to confirm, any other key to cancel".into(),
+    ]));
+
+    let area = frame.area();
+    let width = area.width.min(60);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::bordered()
+                .title(verb)
+                .border_style(Style::default().fg(Color::Yellow)),
+        ),
+        popup,
+    );
+}
+
+/// "Why this rank": every signal's value, weight and contribution to the score.
+fn rank_lines(rank: &Rank) -> Vec<Line<'static>> {
+    let heading = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(format!("Rank {:.3}", rank.total), heading)),
+    ];
+
+    for (signal, value, weight) in &rank.signals {
+        let contribution = value * weight;
+        let style = if contribution > 0.0 {
+            Style::default()
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{:<18}{:.2} x {:.2} = {:.3}",
+                signal, value, weight, contribution
+            ),
+            style,
+        )));
+    }
+    lines
+}
+
+/// `name` padded to `width` columns with the fuzzy-matched characters highlighted.
+///
+/// `matched` holds `char` positions; a grapheme is highlighted when any of
+/// its characters matched.
+fn highlighted_name(name: &str, matched: &[usize], width: usize) -> Vec<Span<'static>> {
+    // Leave a gap before the next column, as the unhighlighted rows do.
+    let shown = text::truncate(name, width - 4);
+    let shown_width = text::width(&shown);
+    let kept = if shown.len() < name.len() {
+        shown.len() - "...".len()
+    } else {
+        name.len()
+    };
+
+    let highlight = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    let mut position = 0;
+
+    for grapheme in name[..kept].graphemes(true) {
+        let chars = grapheme.chars().count();
+        let is_matched = (position..position + chars).any(|i| matched.contains(&i));
+        position += chars;
+        if is_matched != run_matched && !run.is_empty() {
+            let text = std::mem::take(&mut run);
+            spans.push(if run_matched {
+                Span::styled(text, highlight)
+            } else {
+                Span::raw(text)
+            });
+        }
+        run_matched = is_matched;
+        run.push_str(grapheme);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched {
+            Span::styled(run, highlight)
+        } else {
+            Span::raw(run)
+        });
+    }
+
+    spans.push(Span::raw(format!(
+        "{}{}",
+        &shown[kept..],
+        " ".repeat(width.saturating_sub(shown_width))
+    )));
+    spans
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn downgrade_items(app: &App) -> Vec<ListItem<'static>> {
+    let Some(choice) = &app.downgrade else {
+        return Vec::new();
+    };
+    if choice.versions.is_empty() {
+        return vec![ListItem::new(format!(
+            "No cached versions of {} in {}",
+            choice.package,
+            cache::PACMAN_CACHE
+        ))];
+    }
+
+    choice
+        .versions
+        .iter()
+        .map(|archive| {
+            let installed = choice.installed.as_deref() == Some(archive.version.as_str());
+            let marker = if installed { "[installed]" } else { "" };
+
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(
+                    "{: <28} {: <8} {: >10} ",
+                    archive.version,
+                    archive.arch,
+                    format_size(archive.size)
+                )),
+                Span::styled(marker, Style::default().fg(Color::Green)),
+            ]))
+        })
+        .collect()
+}
+
+fn downgrade_details(app: &App) -> Vec<Line<'static>> {
+    let Some(choice) = &app.downgrade else {
+        return Vec::new();
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut fields = vec![
+        ("Package", choice.package.clone()),
+        (
+            "Installed",
+            choice
+                .installed
+                .clone()
+                .unwrap_or_else(|| "not installed".to_string()),
+        ),
+        ("Cached", choice.versions.len().to_string()),
+    ];
+    if let Some(archive) = choice.versions.get(app.selected) {
+        fields.push(("Version", archive.version.clone()));
+        fields.push(("Architecture", archive.arch.clone()));
+        fields.push(("Size", format_size(archive.size)));
+        fields.push(("Archive", archive.path.to_string_lossy().to_string()));
+    }
+
+    fields
+        .into_iter()
+        .map(|(key, value)| {
+            Line::from(vec![
+                Span::styled(format!("{:<15}: ", key), key_style),
+                Span::raw(value),
+            ])
+        })
+        .collect()
+}
+
+fn cache_items(app: &App) -> Vec<ListItem<'static>> {
+    let Some(view) = &app.cache else {
+        return vec![ListItem::new("Scanning the package cache...")];
+    };
+    if view.groups.is_empty() {
+        return vec![ListItem::new("No cached packages found")];
+    }
+
+    view.groups
+        .iter()
+        .map(|group| {
+            let doomed = group
+                .versions
+                .iter()
+                .filter(|p| view.doomed.contains(&p.path))
+                .count();
+            let status = if group.installed {
+                ""
+            } else {
+                " (uninstalled)"
+            };
+            let cache = if group.source == "pacman" {
+                String::new()
+            } else {
+                format!(" [{}]", group.source)
+            };
+
+            let mut spans = vec![Span::raw(format!(
+                "{} {: >3} cached {: >10}",
+                text::pad(&format!("{}{}{}", group.name, cache, status), 28),
+                group.versions.len(),
+                format_size(group.size())
+            ))];
+            if doomed > 0 {
+                spans.push(Span::styled(
+                    format!("  -{}", doomed),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect()
+}
+
+/// Cleanup policy, dry-run totals and the per-version plan for the selected package.
+fn cache_details(app: &App) -> Vec<Line<'static>> {
+    let Some(view) = &app.cache else {
+        return Vec::new();
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let field = |key: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", key), key_style),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![
+        field("Disk usage", format_size(view.total_size())),
+        field("Keep versions", view.policy.keep.to_string()),
+        field(
+            "Uninstalled",
+            if view.policy.remove_uninstalled {
+                "remove all".to_string()
+            } else {
+                "treat like installed".to_string()
+            },
+        ),
+        field(
+            "Dry run",
+            format!(
+                "{} archives, {} to free",
+                view.doomed_archives().count(),
+                format_size(view.doomed_size())
+            ),
+        ),
+    ];
+
+    if view.confirm {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "Delete {} archives? Press y to confirm, any other key to cancel",
+                view.doomed_archives().count()
+            ),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if let Some(group) = view.groups.get(app.selected) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(group.name.clone(), key_style),
+            Span::raw(format!(" in the {} cache", group.source)),
+        ]));
+        for archive in &group.versions {
+            let (action, color) = if view.doomed.contains(&archive.path) {
+                ("remove", Color::Red)
+            } else {
+                ("keep  ", Color::Green)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(action, Style::default().fg(color)),
+                Span::raw(format!(
+                    " {: <24} {: <8} {: >10} {}",
+                    archive.version,
+                    archive.arch,
+                    format_size(archive.size),
+                    archive.source
+                )),
+            ]));
+        }
+    }
+
+    lines
+}
+
+fn basket_items(app: &App) -> Vec<ListItem<'static>> {
+    if app.basket.is_empty() {
+        return vec![ListItem::new(
+            "Basket is empty; press Space on a search result",
+        )];
+    }
+
+    app.basket
+        .iter()
+        .map(|p| {
+            ListItem::new(Line::from(format!(
+                "{} {} {}",
+                text::pad(&p.id.to_string(), 32),
+                text::pad(&p.id.version, 20),
+                p.id.provider
+            )))
+        })
+        .collect()
+}
+
+fn basket_details(app: &App) -> Vec<Line<'static>> {
+    let Some(pkg) = app.basket.get(app.selected) else {
+        return vec![Line::from("Nothing queued")];
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    [
+        ("Name", pkg.id.name.clone()),
+        ("Version", pkg.id.version.clone()),
+        ("Repository", pkg.id.repo.clone()),
+        ("Provider", pkg.id.provider.clone()),
+        ("Description", pkg.description.clone()),
+        ("Queued", app.basket.len().to_string()),
+    ]
+    .into_iter()
+    .map(|(key, value)| {
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", key), key_style),
+            Span::raw(value),
+        ])
+    })
+    .collect()
+}
+
+fn manifest_items(app: &App) -> Vec<ListItem<'static>> {
+    let Some(view) = &app.manifest else {
+        return Vec::new();
+    };
+    if view.diff.missing.is_empty() && view.diff.extra.is_empty() {
+        return vec![ListItem::new("System matches the manifest")];
+    }
+
+    view.rows()
+        .map(|(missing, provider, name)| {
+            let (label, color) = if missing {
+                ("+ missing", Color::Green)
+            } else {
+                ("- extra  ", Color::Yellow)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(label, Style::default().fg(color)),
+                Span::raw(format!(" {} {}", text::pad(name, 28), provider)),
+            ]))
+        })
+        .collect()
+}
+
+fn manifest_details(app: &App) -> Vec<Line<'static>> {
+    let Some(view) = &app.manifest else {
+        return Vec::new();
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut lines: Vec<Line> = [
+        ("Manifest", view.path.clone()),
+        ("Missing", view.diff.missing.len().to_string()),
+        ("Extra", view.diff.extra.len().to_string()),
+    ]
+    .into_iter()
+    .map(|(key, value)| {
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", key), key_style),
+            Span::raw(value),
+        ])
+    })
+    .collect();
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(
+        "Missing packages are listed in the manifest but not installed; extra ones are \
+         installed explicitly but not listed. Press i to install everything missing.",
+    ));
+    lines
+}
+
+fn history_items(app: &App) -> Vec<ListItem<'static>> {
+    if app.history_source == HistorySource::System {
+        return app
+            .history_filtered
+            .iter()
+            .map(|&i| {
+                let event = &app.system_log[i];
+                let color = match event.action {
+                    LogAction::Installed => Color::Green,
+                    LogAction::Upgraded => Color::Cyan,
+                    LogAction::Downgraded => Color::Magenta,
+                    LogAction::Reinstalled => Color::Blue,
+                    LogAction::Removed => Color::Red,
+                };
+
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", event.timestamp.format("%Y-%m-%d %H:%M"))),
+                    Span::styled(
+                        format!("{:<11}", event.action.as_str()),
+                        Style::default().fg(color),
+                    ),
+                    Span::raw(format!(" {}", event.package)),
+                ]))
+            })
+            .collect();
+    }
+
+    app.history_filtered
+        .iter()
+        .map(|&i| {
+            let entry = &app.history[i];
+            let (mark, color) = if entry.success {
+                ("ok  ", Color::Green)
+            } else {
+                ("fail", Color::Red)
+            };
+            let when = entry
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M");
+
+            ListItem::new(Line::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::raw(format!(" {} {}", when, entry.summary())),
+            ]))
+        })
+        .collect()
+}
+
+fn history_details(app: &App, width: u16) -> Vec<Line<'static>> {
+    if app.history_source == HistorySource::System {
+        return log_event_details(app, width);
+    }
+
+    let Some(entry) = app.selected_history() else {
+        return vec![Line::from("No history recorded yet")];
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let status = match (entry.exit_code, entry.success) {
+        (_, true) => "succeeded".to_string(),
+        (Some(code), false) => format!("failed (exit {})", code),
+        (None, false) => "failed to run".to_string(),
+    };
+
+    [
+        (
+            "Time",
+            entry
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        ),
+        ("Operation", entry.summary()),
+        ("Provider", entry.operation.provider.clone()),
+        ("Packages", entry.operation.packages.join(" ")),
+        ("Command", entry.command.clone()),
+        ("Status", status),
+        ("Duration", format!("{:.1}s", entry.duration_secs)),
+    ]
+    .into_iter()
+    .map(|(key, value)| {
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", key), key_style),
+            Span::raw(value),
+        ])
+    })
+    .collect()
+}
+
+/// The selected pacman.log event followed by the details of the package it touched.
+fn log_event_details(app: &App, width: u16) -> Vec<Line<'static>> {
+    let Some(event) = app.selected_log_event() else {
+        return vec![Line::from("No pacman.log entries")];
+    };
+
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", "Time"), key_style),
+            Span::raw(event.timestamp.format("%Y-%m-%d %H:%M:%S %z").to_string()),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<15}: ", "Event"), key_style),
+            Span::raw(event.summary()),
+        ]),
+        Line::from(""),
+    ];
+
+    match &app.details {
+        Some(details) => lines.extend(package_details_lines(details, app.details_target(), width)),
+        None if app.details_loading => lines.push(Line::from("Loading details...")),
+        None => lines.push(Line::from("Package is no longer available")),
+    }
+
+    lines
+}
+
+fn draw_job_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(job) = app.job.as_ref() else {
+        return;
+    };
+
+    let block = Block::bordered();
+
+    let elapsed = job.elapsed().as_secs_f32();
+    let (title, border_color) = match job.status() {
+        JobStatus::Running => (
+            format!(
+                "Running: {} ({:.0}s) — Ctrl-b background, PgUp/PgDn scroll",
+                job.command_line, elapsed
+            ),
+            Color::Yellow,
+        ),
+        JobStatus::Exited { code, success } => (
+            format!(
+                "{}: {} (exit {}, {:.1}s) — Enter to close",
+                if success { "Finished" } else { "Failed" },
+                job.command_line,
+                code,
+                elapsed
+            ),
+            if success { Color::Green } else { Color::Red },
+        ),
+        JobStatus::Failed(e) => (
+            format!("Error: {} ({}) — Enter to close", job.command_line, e),
+            Color::Red,
+        ),
+    };
+
+    frame.render_widget(
+        Paragraph::new(job.lines()).block(
+            block
+                .title(title)
+                .border_style(Style::default().fg(border_color)),
+        ),
+        area,
+    );
 }
@@ -0,0 +1,114 @@
+//! Messages driving the app's event loop.
+//!
+//! Everything that changes `App` arrives as a `Message` on one channel:
+//! terminal input, periodic ticks, and the results of work done off the
+//! event loop (searches, package details, build reviews, the local database,
+//! history and cache reads behind the other views, finished commands).
+
+use crate::history::HistoryEntry;
+use crate::history::pacman_log::LogEvent;
+use crate::managers::analysis::LocalPackage;
+use crate::managers::cache::{CacheGroup, CachedPackage};
+use crate::managers::details::PackageDetails;
+use crate::managers::manifest::ManifestDiff;
+use crate::managers::review::AurReview;
+use crate::managers::{Package, PackageId};
+use crate::ui::app::View;
+use crate::ui::pty::JobStatus;
+use crossterm::event::{Event, EventStream, KeyEvent};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// How often the UI redraws and checks for a debounced search.
+pub const TICK_RATE: Duration = Duration::from_millis(100);
+
+pub enum Message {
+    KeyInput(KeyEvent),
+    /// Grouped results for the query they were searched with.
+    SearchResults {
+        query: String,
+        groups: Vec<Vec<Package>>,
+    },
+    /// `details` is `None` when the package manager knows nothing about `id`.
+    DetailsLoaded {
+        id: PackageId,
+        details: Option<Box<PackageDetails>>,
+    },
+    /// `files` is what `id` installs, or why it couldn't be listed.
+    FilesLoaded {
+        id: PackageId,
+        files: Result<Vec<String>, String>,
+    },
+    /// Packages owning `target`, a path or command typed in the owners view.
+    OwnersFound {
+        target: String,
+        packages: Vec<Package>,
+    },
+    /// The build files of an AUR package to review before it is built.
+    ReviewLoaded {
+        name: String,
+        review: Result<Box<AurReview>, String>,
+    },
+    /// The local package database, read for the orphans or leaves `view`.
+    AnalysisLoaded {
+        view: View,
+        db: Vec<LocalPackage>,
+    },
+    /// trx's own history and the events in pacman.log.
+    HistoryLoaded {
+        history: Vec<HistoryEntry>,
+        system_log: Vec<LogEvent>,
+    },
+    /// The installed and cached versions of `package`, to downgrade it.
+    VersionsLoaded {
+        package: String,
+        installed: Option<String>,
+        versions: Vec<CachedPackage>,
+    },
+    /// Every cached archive, grouped by package.
+    CacheScanned(Vec<CacheGroup>),
+    /// How the manifest at `path` differs from the system, or why it couldn't be read.
+    ManifestLoaded {
+        path: String,
+        diff: Result<ManifestDiff, String>,
+    },
+    /// AUR packages with updates, to review before upgrading them.
+    AurUpdates(Result<Vec<String>, String>),
+    Tick,
+    /// The command running in the job pane has exited.
+    CommandFinished(JobStatus),
+}
+
+/// Where the event loop's key presses and ticks come from.
+pub trait EventSource {
+    /// Starts sending messages to `tx`; stops once the app drops the receiver.
+    fn spawn(self, tx: UnboundedSender<Message>);
+}
+
+/// Key presses from the real terminal plus a steady tick.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn spawn(self, tx: UnboundedSender<Message>) {
+        tokio::spawn(async move {
+            let mut events = EventStream::new();
+            let mut ticks = tokio::time::interval(TICK_RATE);
+
+            loop {
+                let message = tokio::select! {
+                    _ = ticks.tick() => Message::Tick,
+                    event = events.next() => match event {
+                        Some(Ok(Event::Key(key))) => Message::KeyInput(key),
+                        // Resizes are picked up by the next draw.
+                        Some(Ok(_)) => continue,
+                        Some(Err(_)) | None => break,
+                    },
+                };
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,190 @@
+//! Syntax highlighting for the build files shown before an AUR build.
+//!
+//! PKGBUILDs and install scripts are bash; the highlighter only knows
+//! enough of it to make comments, strings, variables and functions stand
+//! out, and never fails on input it doesn't understand.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+const PLAIN: Style = Style::new();
+const COMMENT: Style = Style::new().fg(Color::DarkGray);
+const STRING: Style = Style::new().fg(Color::Green);
+const VARIABLE: Style = Style::new().fg(Color::Cyan);
+const KEYWORD: Style = Style::new().fg(Color::Magenta);
+const ASSIGNMENT: Style = Style::new().fg(Color::Yellow);
+const FUNCTION: Style = Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD);
+
+const KEYWORDS: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "local", "return",
+    "select", "then", "until", "while",
+];
+
+/// Appends `text` to the last span when it has the same style.
+fn push(spans: &mut Vec<Span<'static>>, text: &str, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+        _ => spans.push(Span::styled(text.to_string(), style)),
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Length of the `$name`, `${...}` or `$1` at the start of `text`.
+fn variable_len(text: &str) -> usize {
+    let rest = &text[1..];
+    if rest.starts_with('{') {
+        return rest.find('}').map_or(text.len(), |end| end + 2);
+    }
+    let name = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        .count();
+    match rest.bytes().next() {
+        _ if name > 0 => name + 1,
+        Some(b'@' | b'*' | b'#' | b'?' | b'$' | b'!') => 2,
+        _ => 1,
+    }
+}
+
+/// Length of the quoted text at the start of `text` up to and including
+/// the closing `quote`, and whether it was closed on this line.
+fn quoted_len(text: &str, quote: char) -> (usize, bool) {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return (i + 1, true);
+        }
+    }
+    (text.len(), false)
+}
+
+/// Double-quoted text, with the variables it expands picked out.
+fn double_quoted(text: &str, spans: &mut Vec<Span<'static>>) {
+    let bytes = text.as_bytes();
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => {
+                push(spans, &text[start..i], STRING);
+                let len = variable_len(&text[i..]);
+                push(spans, &text[i..i + len], VARIABLE);
+                i += len;
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    push(spans, &text[start..], STRING);
+}
+
+/// One line of bash; `quote` carries a string left open into the next line.
+fn shell_line(line: &str, quote: &mut Option<char>) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let before = &line[..line.len() - rest.len()];
+
+        if let Some(q) = *quote {
+            let (len, closed) = quoted_len(rest, q);
+            if q == '"' {
+                double_quoted(&rest[..len], &mut spans);
+            } else {
+                push(&mut spans, &rest[..len], STRING);
+            }
+            if closed {
+                *quote = None;
+            }
+            rest = &rest[len..];
+        } else if c == '#' && (before.is_empty() || before.ends_with(char::is_whitespace)) {
+            push(&mut spans, rest, COMMENT);
+            break;
+        } else if c == '\\' {
+            // An escaped character, quotes included, is taken literally.
+            let len = rest[1..]
+                .chars()
+                .next()
+                .map_or(1, |next| 1 + next.len_utf8());
+            push(&mut spans, &rest[..len], PLAIN);
+            rest = &rest[len..];
+        } else if c == '\'' || c == '"' {
+            push(&mut spans, &rest[..1], STRING);
+            *quote = Some(c);
+            rest = &rest[1..];
+        } else if c == '$' {
+            let len = variable_len(rest);
+            push(&mut spans, &rest[..len], VARIABLE);
+            rest = &rest[len..];
+        } else if is_word(c) {
+            let len = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+            let (word, after) = rest.split_at(len);
+            let command_start = before.trim_end().is_empty()
+                || before.trim_end().ends_with([';', '&', '|', '{', '(']);
+            let identifier = !word.contains('-') && !word.starts_with(|c: char| c.is_ascii_digit());
+            let style = if identifier
+                && before.trim().is_empty()
+                && (after.starts_with('=') || after.starts_with("+="))
+            {
+                ASSIGNMENT
+            } else if after.starts_with("()") {
+                FUNCTION
+            } else if (command_start && KEYWORDS.contains(&word))
+                || (word == "in" && (before.contains("for ") || before.contains("case ")))
+            {
+                KEYWORD
+            } else {
+                PLAIN
+            };
+            push(&mut spans, word, style);
+            rest = after;
+        } else {
+            push(&mut spans, &rest[..c.len_utf8()], PLAIN);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Line::from(spans)
+}
+
+/// A bash script such as a PKGBUILD, one line per source line.
+pub fn shell(source: &str) -> Vec<Line<'static>> {
+    let mut quote = None;
+    source
+        .lines()
+        .map(|line| shell_line(line, &mut quote))
+        .collect()
+}
+
+/// `git diff` output with added lines green and removed lines red.
+pub fn diff(text: &str) -> Vec<Line<'static>> {
+    text.lines()
+        .map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::new().add_modifier(Modifier::BOLD)
+            } else if line.starts_with('+') {
+                Style::new().fg(Color::Green)
+            } else if line.starts_with('-') {
+                Style::new().fg(Color::Red)
+            } else if line.starts_with("@@") {
+                Style::new().fg(Color::Cyan)
+            } else if line.starts_with("diff ") || line.starts_with("index ") {
+                COMMENT
+            } else {
+                PLAIN
+            };
+            Line::styled(line.to_string(), style)
+        })
+        .collect()
+}
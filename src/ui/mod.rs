@@ -1,3 +1,10 @@
 pub mod app;
 pub mod draw;
+pub mod event;
+pub mod highlight;
 pub mod input;
+pub mod pty;
+pub mod text;
+
+#[cfg(test)]
+mod tests;
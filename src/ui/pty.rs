@@ -0,0 +1,259 @@
+use crate::managers::Operation;
+use portable_pty::{CommandBuilder, MasterPty, PtySize, native_pty_system};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+const SCROLLBACK_LINES: usize = 5000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Exited { code: u32, success: bool },
+    Failed(String),
+}
+
+/// A package operation running inside a pseudo-terminal, rendered by the job pane.
+pub struct PtyJob {
+    pub operation: Operation,
+    pub command_line: String,
+    parser: Arc<Mutex<vt100::Parser>>,
+    status: watch::Receiver<JobStatus>,
+    finished_at: Arc<Mutex<Option<Instant>>>,
+    writer: Box<dyn Write + Send>,
+    master: Box<dyn MasterPty + Send>,
+    started_at: Instant,
+    size: (u16, u16),
+}
+
+impl PtyJob {
+    pub fn spawn(
+        operation: Operation,
+        cmd: &str,
+        args: &[&str],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let size = (24, 80);
+        let pair = native_pty_system().openpty(PtySize {
+            rows: size.0,
+            cols: size.1,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+
+        let mut builder = CommandBuilder::new(cmd);
+        builder.args(args);
+        if let Ok(cwd) = std::env::current_dir() {
+            builder.cwd(cwd);
+        }
+        let mut child = pair.slave.spawn_command(builder)?;
+        // Only the child keeps the slave side open, so the reader sees EOF when it exits.
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(
+            size.0,
+            size.1,
+            SCROLLBACK_LINES,
+        )));
+        let (status_tx, status) = watch::channel(JobStatus::Running);
+        let finished_at = Arc::new(Mutex::new(None));
+
+        thread::spawn({
+            let parser = Arc::clone(&parser);
+            move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => parser.lock().unwrap().process(&buf[..n]),
+                    }
+                }
+            }
+        });
+
+        thread::spawn({
+            let finished_at = Arc::clone(&finished_at);
+            move || {
+                let result = match child.wait() {
+                    Ok(exit) => JobStatus::Exited {
+                        code: exit.exit_code(),
+                        success: exit.success(),
+                    },
+                    Err(e) => JobStatus::Failed(e.to_string()),
+                };
+                *finished_at.lock().unwrap() = Some(Instant::now());
+                status_tx.send_replace(result);
+            }
+        });
+
+        Ok(Self {
+            operation,
+            command_line: format!("{} {}", cmd, args.join(" ")),
+            parser,
+            status,
+            finished_at,
+            writer,
+            master: pair.master,
+            started_at: Instant::now(),
+            size,
+        })
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Resolves with the final status once the command has exited.
+    pub fn exited(&self) -> impl Future<Output = JobStatus> + Send + 'static {
+        let mut status = self.status.clone();
+        async move {
+            match status.wait_for(|s| *s != JobStatus::Running).await {
+                Ok(status) => status.clone(),
+                Err(_) => JobStatus::Failed("lost track of the command".to_string()),
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status() == JobStatus::Running
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match *self.finished_at.lock().unwrap() {
+            Some(end) => end - self.started_at,
+            None => self.started_at.elapsed(),
+        }
+    }
+
+    /// Keeps the pseudo-terminal the same size as the pane it is drawn in.
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        if (rows, cols) == self.size || rows == 0 || cols == 0 {
+            return;
+        }
+        self.size = (rows, cols);
+        self.parser.lock().unwrap().set_size(rows, cols);
+        let _ = self.master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        });
+    }
+
+    pub fn scroll(&mut self, lines: isize) {
+        let mut parser = self.parser.lock().unwrap();
+        let current = parser.screen().scrollback() as isize;
+        parser.set_scrollback((current + lines).max(0) as usize);
+    }
+
+    /// Forwards a key press to the running program, e.g. a sudo password or `[Y/n]`.
+    pub fn send_key(&mut self, key: KeyEvent) {
+        let bytes: Vec<u8> = match key.code {
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let c = c.to_ascii_lowercase();
+                if c.is_ascii_lowercase() {
+                    vec![c as u8 - b'a' + 1]
+                } else {
+                    return;
+                }
+            }
+            KeyCode::Char(c) => c.to_string().into_bytes(),
+            KeyCode::Enter => vec![b'\r'],
+            KeyCode::Backspace => vec![0x7f],
+            KeyCode::Tab => vec![b'\t'],
+            KeyCode::Esc => vec![0x1b],
+            KeyCode::Up => b"\x1b[A".to_vec(),
+            KeyCode::Down => b"\x1b[B".to_vec(),
+            KeyCode::Right => b"\x1b[C".to_vec(),
+            KeyCode::Left => b"\x1b[D".to_vec(),
+            _ => return,
+        };
+
+        if self.writer.write_all(&bytes).is_ok() {
+            let _ = self.writer.flush();
+            // Typing snaps the view back to the live screen.
+            self.parser.lock().unwrap().set_scrollback(0);
+        }
+    }
+
+    /// Converts the current vt100 screen into styled ratatui lines.
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let parser = self.parser.lock().unwrap();
+        let screen = parser.screen();
+        let (rows, cols) = screen.size();
+
+        let mut lines = Vec::with_capacity(rows as usize);
+        for row in 0..rows {
+            let mut spans: Vec<Span> = Vec::new();
+            let mut text = String::new();
+            let mut style = Style::default();
+
+            for col in 0..cols {
+                let Some(cell) = screen.cell(row, col) else {
+                    continue;
+                };
+                if cell.is_wide_continuation() {
+                    continue;
+                }
+
+                let cell_style = cell_style(cell);
+                if cell_style != style && !text.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut text), style));
+                }
+                style = cell_style;
+
+                if cell.has_contents() {
+                    text.push_str(&cell.contents());
+                } else {
+                    text.push(' ');
+                }
+            }
+
+            spans.push(Span::styled(text.trim_end().to_string(), style));
+            lines.push(Line::from(spans));
+        }
+
+        lines
+    }
+}
+
+fn convert_color(color: vt100::Color) -> Option<Color> {
+    match color {
+        vt100::Color::Default => None,
+        vt100::Color::Idx(i) => Some(Color::Indexed(i)),
+        vt100::Color::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    }
+}
+
+fn cell_style(cell: &vt100::Cell) -> Style {
+    let mut style = Style::default();
+    if let Some(fg) = convert_color(cell.fgcolor()) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = convert_color(cell.bgcolor()) {
+        style = style.bg(bg);
+    }
+    if cell.bold() {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if cell.italic() {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if cell.underline() {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    if cell.inverse() {
+        style = style.add_modifier(Modifier::REVERSED);
+    }
+    style
+}
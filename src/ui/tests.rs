@@ -0,0 +1,599 @@
+//! Drives `App` with scripted keys against a fake backend and checks what
+//! it draws on a `TestBackend`.
+
+use crate::config;
+use crate::history;
+use crate::managers::backend::Backend;
+use crate::managers::details::PackageDetails;
+use crate::managers::review::{self, AurReview, Build, Changes};
+use crate::managers::{Operation, OperationKind, Package, PackageId};
+use crate::ui::app::{App, FilesView};
+use crate::ui::event::{EventSource, Message};
+use crate::ui::highlight;
+use crate::ui::pty::PtyJob;
+use futures::future::{self, BoxFuture};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Color;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+/// Wide enough for the whole help line, basket count included.
+const WIDTH: u16 = 200;
+
+/// Serves a fixed package list and records what it was asked to install.
+struct FakeBackend {
+    packages: Vec<Package>,
+    installs: Arc<Mutex<Vec<Vec<String>>>>,
+    /// Whether packages are built from source, behind a review.
+    reviews: bool,
+}
+
+impl Backend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn search(&self, _query: &str) -> Vec<Package> {
+        // The query language filters and ranks whatever the backend returns.
+        self.packages.clone()
+    }
+
+    fn list_all(&self) -> Vec<Package> {
+        self.packages.clone()
+    }
+
+    fn explicit_packages(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn details(&self, id: &PackageId) -> BoxFuture<'static, Option<PackageDetails>> {
+        let description = self
+            .packages
+            .iter()
+            .find(|p| p.id == *id)
+            .map(|p| p.description.clone())
+            .unwrap_or_default();
+        let details = PackageDetails {
+            name: id.name.clone(),
+            version: id.version.clone(),
+            description,
+            packager: Some(format!("Packager of {}", id.name)),
+            ..PackageDetails::default()
+        };
+        Box::pin(future::ready(Some(details)))
+    }
+
+    fn install(
+        &self,
+        names: &HashSet<String>,
+    ) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        let mut names: Vec<String> = names.iter().cloned().collect();
+        names.sort();
+        self.installs.lock().unwrap().push(names.clone());
+        // A job that succeeds straight away, as if everything got installed.
+        let operation = Operation::new(OperationKind::Install, "fake", names);
+        Ok(Some(PtyJob::spawn(operation, "true", &[])?))
+    }
+
+    fn review(&self, name: &str) -> Option<BoxFuture<'static, Result<AurReview, String>>> {
+        let review = AurReview {
+            name: name.to_string(),
+            base: name.to_string(),
+            revision: format!("{}-0123456789", name),
+            files: vec![("PKGBUILD".to_string(), format!("pkgname={}\n", name))],
+            changes: Changes::New,
+            comments: Ok(Vec::new()),
+            depends: match name {
+                "grep" => vec!["libsigsegv-git".to_string()],
+                _ => Vec::new(),
+            },
+        };
+        self.reviews
+            .then(|| Box::pin(future::ready(Ok(review))) as BoxFuture<'static, _>)
+    }
+
+    fn build(&self, builds: &[Build]) -> Result<Option<PtyJob>, Box<dyn std::error::Error>> {
+        // In build order rather than sorted.
+        let names: Vec<String> = builds.iter().map(|b| b.name.clone()).collect();
+        self.installs.lock().unwrap().push(names.clone());
+        let operation = Operation::new(OperationKind::Install, "fake", names);
+        Ok(Some(PtyJob::spawn(operation, "true", &[])?))
+    }
+}
+
+fn fake_package(name: &str, description: &str) -> Package {
+    Package::new(PackageId::new("fake", "extra", name, "1.0-1"), description)
+}
+
+/// Too long to fit the details pane without scrolling.
+fn long_description() -> String {
+    (0..400)
+        .map(|i| format!("word{i}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keeps history, basket picks and settings away from the user's own files.
+fn isolate_user_files() {
+    let dir = std::env::temp_dir().join(format!("trx-tests-{}", std::process::id()));
+    history::use_data_dir(dir.join("data"));
+    config::use_config_dir(dir.join("config"));
+}
+
+struct Harness {
+    app: App,
+    terminal: Terminal<TestBackend>,
+    installs: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::with_reviews(false)
+    }
+
+    fn with_reviews(reviews: bool) -> Self {
+        isolate_user_files();
+        let installs = Arc::new(Mutex::new(Vec::new()));
+        let backend = FakeBackend {
+            packages: vec![
+                fake_package("ripgrep", "A search tool"),
+                fake_package("ripgrep-all", "ripgrep, but also in PDFs and archives"),
+                fake_package("grep", "GNU grep"),
+                fake_package("firefox", "Web browser"),
+                fake_package("manual", &long_description()),
+            ],
+            installs: Arc::clone(&installs),
+            reviews,
+        };
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            app: App::new(tx, rx, vec![Arc::new(backend)]),
+            terminal: Terminal::new(TestBackend::new(WIDTH, 30)).unwrap(),
+            installs,
+        }
+    }
+
+    fn press(&mut self, code: KeyCode) {
+        self.app
+            .update(Message::KeyInput(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.press(KeyCode::Char(c));
+        }
+    }
+
+    /// Searches for `query` from the normal mode and waits for the results.
+    async fn search(&mut self, query: &str) {
+        self.press(KeyCode::Char('e'));
+        self.type_text(query);
+        self.press(KeyCode::Enter);
+        self.settle().await;
+    }
+
+    /// Lets the search debounce pass, then handles everything the app's
+    /// tasks send back until they go quiet.
+    async fn settle(&mut self) {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        self.app.update(Message::Tick);
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(300), self.app.next_message()).await
+        {
+            self.app.update(message);
+        }
+    }
+
+    /// The rendered screen, one line per row.
+    fn screen(&mut self) -> String {
+        self.app.render(&mut self.terminal).unwrap();
+        buffer_text(&self.terminal)
+    }
+
+    fn installs(&self) -> Vec<Vec<String>> {
+        self.installs.lock().unwrap().clone()
+    }
+}
+
+fn buffer_text(terminal: &Terminal<TestBackend>) -> String {
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The row of the package list, the screen's left half, showing `name`.
+fn row(screen: &str, name: &str) -> Option<String> {
+    screen
+        .lines()
+        .map(|line| line.chars().take(WIDTH as usize / 2).collect::<String>())
+        .find(|line| line.split_whitespace().any(|word| word == name))
+}
+
+#[tokio::test]
+async fn search_lists_matches_best_first() {
+    let mut h = Harness::new();
+    h.search("rip").await;
+    let screen = h.screen();
+
+    let first = row(&screen, "ripgrep").expect("ripgrep is listed");
+    assert!(first.contains("» [ ] ripgrep"), "{screen}");
+    assert!(row(&screen, "ripgrep-all").is_some(), "{screen}");
+    assert!(row(&screen, "firefox").is_none(), "{screen}");
+    assert!(row(&screen, "grep").is_none(), "{screen}");
+}
+
+#[tokio::test]
+async fn moving_the_selection_loads_its_details() {
+    let mut h = Harness::new();
+    h.search("rip").await;
+    assert!(h.screen().contains("Packager of ripgrep "));
+
+    h.press(KeyCode::Char('j'));
+    let screen = h.screen();
+    assert!(
+        row(&screen, "ripgrep-all")
+            .unwrap()
+            .contains("» [ ] ripgrep-all"),
+        "{screen}"
+    );
+    // Shown until the lookup answers through the message channel.
+    assert!(screen.contains("Loading details..."), "{screen}");
+
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("Packager of ripgrep-all"), "{screen}");
+    assert!(!screen.contains("Loading details..."), "{screen}");
+}
+
+#[tokio::test]
+async fn long_details_wrap_to_the_pane_and_scroll() {
+    let mut h = Harness::new();
+    h.search("manual").await;
+    let top = h.screen();
+    assert!(top.contains("manual 1.0-1  extra via fake"), "{top}");
+    assert!(top.contains("word0 word1"), "{top}");
+    assert!(!top.contains("Packager of manual"), "{top}");
+    // Wrapped to the pane, so nothing is cut off at its right border.
+    let pane_width = WIDTH as usize / 2;
+    let words = top
+        .lines()
+        .map(|line| line.chars().skip(pane_width).collect::<String>())
+        .flat_map(|line| {
+            line.split_whitespace()
+                .filter(|w| w.starts_with("word"))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert!(words.windows(2).all(|w| {
+        let n = |w: &str| w["word".len()..].parse::<usize>().unwrap();
+        n(&w[1]) == n(&w[0]) + 1
+    }));
+
+    for _ in 0..20 {
+        h.press(KeyCode::PageDown);
+    }
+    let bottom = h.screen();
+    assert!(bottom.contains("Packager of manual"), "{bottom}");
+    assert!(!bottom.contains("manual 1.0-1  extra via fake"), "{bottom}");
+
+    // Scrolling stopped at the end, so one page up moves straight away.
+    h.press(KeyCode::PageUp);
+    assert_ne!(h.screen(), bottom);
+}
+
+#[tokio::test]
+async fn files_tab_replaces_the_details_until_esc() {
+    let mut h = Harness::new();
+    h.search("rip").await;
+    h.press(KeyCode::Char('f'));
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("Files of ripgrep"), "{screen}");
+    // Not installed and not from the sync repositories.
+    assert!(
+        screen.contains("File lists of fake packages are known once installed"),
+        "{screen}"
+    );
+
+    // The list keeps its selection while the tab has the keys.
+    h.press(KeyCode::Char('j'));
+    h.press(KeyCode::Esc);
+    let screen = h.screen();
+    assert!(!screen.contains("Files of ripgrep"), "{screen}");
+    assert!(screen.contains("Packager of ripgrep "), "{screen}");
+}
+
+#[tokio::test]
+async fn cursor_follows_wide_characters() {
+    let mut h = Harness::new();
+    h.press(KeyCode::Char('e'));
+    h.type_text("日本");
+    h.screen();
+    // Two cells each, after the search box's left border.
+    assert_eq!(h.terminal.get_cursor_position().unwrap().x, 5);
+
+    h.press(KeyCode::Left);
+    h.screen();
+    assert_eq!(h.terminal.get_cursor_position().unwrap().x, 3);
+}
+
+#[tokio::test]
+async fn install_waits_for_confirmation() {
+    let mut h = Harness::new();
+    h.search("rip").await;
+    h.press(KeyCode::Char(' '));
+    assert!(h.screen().contains("basket (1)"));
+
+    h.press(KeyCode::Char('i'));
+    let screen = h.screen();
+    assert!(screen.contains("Install 1 packages?"), "{screen}");
+    assert!(screen.contains("extra/ripgrep (fake)"), "{screen}");
+    assert!(h.installs().is_empty());
+
+    h.press(KeyCode::Char('y'));
+    let screen = h.screen();
+    assert_eq!(h.installs(), vec![vec!["ripgrep".to_string()]]);
+    assert!(!screen.contains("Install 1 packages?"), "{screen}");
+    // Packages leave the basket once the install has succeeded.
+    assert!(screen.contains("basket (1)"), "{screen}");
+
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("basket (0)"), "{screen}");
+}
+
+#[tokio::test]
+async fn any_other_key_cancels_the_install() {
+    let mut h = Harness::new();
+    h.search("rip").await;
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('i'));
+    h.press(KeyCode::Char('n'));
+
+    let screen = h.screen();
+    assert!(!screen.contains("Install 1 packages?"), "{screen}");
+    assert!(screen.contains("basket (1)"), "{screen}");
+    assert!(h.installs().is_empty());
+}
+
+#[tokio::test]
+async fn source_builds_wait_for_an_approved_review() {
+    let mut h = Harness::with_reviews(true);
+    h.search("rip").await;
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('j'));
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('i'));
+    h.press(KeyCode::Char('y'));
+    let screen = h.screen();
+    assert!(
+        screen.contains("Review ripgrep before building (1 more queued)"),
+        "{screen}"
+    );
+
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("pkgname=ripgrep"), "{screen}");
+    assert!(screen.contains("Never reviewed before"), "{screen}");
+    assert!(h.installs().is_empty());
+
+    // Skipped packages are left out of the build.
+    h.press(KeyCode::Char('s'));
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("pkgname=ripgrep-all"), "{screen}");
+    assert!(h.installs().is_empty());
+
+    h.press(KeyCode::Char('a'));
+    let screen = h.screen();
+    assert!(!screen.contains("before building"), "{screen}");
+    assert_eq!(h.installs(), vec![vec!["ripgrep-all".to_string()]]);
+    assert_eq!(
+        review::load_reviewed()
+            .get("ripgrep-all")
+            .map(String::as_str),
+        Some("ripgrep-all-0123456789")
+    );
+    assert!(!review::load_reviewed().contains_key("ripgrep"));
+}
+
+#[tokio::test]
+async fn aur_dependencies_are_reviewed_and_built_first() {
+    let mut h = Harness::with_reviews(true);
+    h.search("grep").await;
+    assert_eq!(h.app.packages[h.app.selected].id.name, "grep");
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('i'));
+    h.press(KeyCode::Char('y'));
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("Needs from the AUR"), "{screen}");
+    assert!(screen.contains("libsigsegv-git"), "{screen}");
+
+    h.press(KeyCode::Char('a'));
+    h.settle().await;
+    let screen = h.screen();
+    assert!(screen.contains("pkgname=libsigsegv-git"), "{screen}");
+    assert!(h.installs().is_empty());
+
+    h.press(KeyCode::Char('a'));
+    assert_eq!(
+        h.installs(),
+        vec![vec!["libsigsegv-git".to_string(), "grep".to_string()]]
+    );
+}
+
+#[tokio::test]
+async fn cancelling_a_review_builds_nothing() {
+    let mut h = Harness::with_reviews(true);
+    h.search("firefox").await;
+    h.press(KeyCode::Char(' '));
+    h.press(KeyCode::Char('i'));
+    h.press(KeyCode::Char('y'));
+    h.settle().await;
+    h.press(KeyCode::Esc);
+
+    let screen = h.screen();
+    assert!(!screen.contains("Review firefox"), "{screen}");
+    // Still there to try again.
+    assert!(screen.contains("basket (1)"), "{screen}");
+    assert!(h.installs().is_empty());
+}
+
+/// Plays keys into the event loop with pauses for the app to catch up.
+struct Script(Vec<(KeyCode, Duration)>);
+
+impl EventSource for Script {
+    fn spawn(self, tx: UnboundedSender<Message>) {
+        tokio::spawn(async move {
+            for (code, pause) in self.0 {
+                let key = KeyEvent::new(code, KeyModifiers::NONE);
+                if tx.send(Message::KeyInput(key)).is_err() {
+                    return;
+                }
+                tokio::time::sleep(pause).await;
+                let _ = tx.send(Message::Tick);
+            }
+        });
+    }
+}
+
+#[tokio::test]
+async fn run_loop_handles_scripted_keys_until_quit() {
+    isolate_user_files();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let backend = FakeBackend {
+        packages: vec![fake_package("firefox", "Web browser")],
+        installs: Arc::default(),
+        reviews: false,
+    };
+    let app = App::new(tx, rx, vec![Arc::new(backend)]);
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, 30)).unwrap();
+
+    let none = Duration::ZERO;
+    let script = Script(vec![
+        (KeyCode::Char('e'), none),
+        (KeyCode::Char('f'), none),
+        (KeyCode::Char('f'), none),
+        (KeyCode::Enter, Duration::from_millis(150)),
+        // Time for the search to answer before quitting.
+        (KeyCode::Null, Duration::from_millis(300)),
+        (KeyCode::Char('q'), none),
+    ]);
+    tokio::time::timeout(Duration::from_secs(5), app.run(&mut terminal, script))
+        .await
+        .expect("q quits the loop")
+        .unwrap();
+
+    let screen = buffer_text(&terminal);
+    assert!(
+        row(&screen, "firefox").unwrap().contains("» [ ] firefox"),
+        "{screen}"
+    );
+}
+
+fn files_view(paths: &[&str]) -> FilesView {
+    FilesView {
+        id: PackageId::new("fake", "extra", "ripgrep", "1.0-1"),
+        paths: Some(Ok(paths.iter().map(|p| p.to_string()).collect())),
+        filter: String::new(),
+        filtering: false,
+        collapsed: HashSet::new(),
+        selected: 0,
+    }
+}
+
+const RIPGREP_FILES: &[&str] = &[
+    "/usr/",
+    "/usr/bin/",
+    "/usr/bin/rg",
+    "/usr/share/",
+    "/usr/share/doc/",
+    "/usr/share/doc/ripgrep/",
+    "/usr/share/doc/ripgrep/GUIDE.md",
+    "/usr/share/man/",
+    "/usr/share/man/man1/",
+    "/usr/share/man/man1/rg.1.gz",
+];
+
+#[test]
+fn file_tree_indents_by_depth_and_folds_directories() {
+    let mut view = files_view(RIPGREP_FILES);
+    let rows = view.rows();
+    let tree: Vec<(usize, &str)> = rows.iter().map(|r| (r.depth, r.name)).collect();
+    assert_eq!(&tree[..3], [(0, "usr/"), (1, "bin/"), (2, "rg")]);
+    assert_eq!(tree[6], (4, "GUIDE.md"));
+
+    view.collapsed.insert("/usr/share/".to_string());
+    let names: Vec<&str> = view.rows().iter().map(|r| r.name).collect();
+    assert_eq!(names, ["usr/", "bin/", "rg", "share/"]);
+    assert!(view.rows()[3].collapsed);
+}
+
+#[test]
+fn file_filter_keeps_matches_and_their_directories() {
+    let mut view = files_view(RIPGREP_FILES);
+    // Filtering looks inside folded directories too.
+    view.collapsed.insert("/usr/share/".to_string());
+    view.filter = "guide".to_string();
+
+    let rows = view.rows();
+    let paths: Vec<&str> = rows.iter().map(|r| r.path).collect();
+    assert_eq!(
+        paths,
+        [
+            "/usr/",
+            "/usr/share/",
+            "/usr/share/doc/",
+            "/usr/share/doc/ripgrep/",
+            "/usr/share/doc/ripgrep/GUIDE.md",
+        ]
+    );
+    // Positions are within the file name, not the whole path.
+    assert_eq!(rows[4].matched, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn pkgbuild_highlighting_picks_out_shell_syntax() {
+    let lines = highlight::shell(
+        "pkgname=yay # the AUR helper\n\
+         depends=(\"git\" 'go')\n\
+         package() {\n\
+         \x20 install -Dm755 \"$srcdir/${pkgname}\" \"$pkgdir/usr/bin/yay\"\n\
+         }",
+    );
+    assert_eq!(lines.len(), 5);
+    let spans = |line: usize| -> Vec<(String, Option<Color>)> {
+        lines[line]
+            .spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style.fg))
+            .collect()
+    };
+
+    assert_eq!(
+        spans(0),
+        [
+            ("pkgname".to_string(), Some(Color::Yellow)),
+            ("=yay ".to_string(), None),
+            ("# the AUR helper".to_string(), Some(Color::DarkGray)),
+        ]
+    );
+    assert!(spans(1).contains(&("'go'".to_string(), Some(Color::Green))));
+    assert_eq!(spans(2)[0], ("package".to_string(), Some(Color::Blue)));
+    assert!(spans(3).contains(&("$srcdir".to_string(), Some(Color::Cyan))));
+    assert!(spans(3).contains(&("${pkgname}".to_string(), Some(Color::Cyan))));
+    assert!(spans(3).contains(&("/usr/bin/yay\"".to_string(), Some(Color::Green))));
+}
@@ -0,0 +1,44 @@
+//! Display-width-aware string helpers.
+//!
+//! Package names and versions may contain wide (CJK, emoji) or combining
+//! characters, so columns are measured in terminal cells and cut between
+//! grapheme clusters rather than at byte or `char` offsets.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: &str = "...";
+
+/// Terminal cells `s` occupies.
+pub fn width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// `s` cut to at most `max` cells, ending in `...` when anything was dropped.
+pub fn truncate(s: &str, max: usize) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+
+    let budget = max.saturating_sub(ELLIPSIS.len());
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let w = width(grapheme);
+        if used + w > budget {
+            break;
+        }
+        out.push_str(grapheme);
+        used += w;
+    }
+    out.push_str(&ELLIPSIS[..max.min(ELLIPSIS.len())]);
+    out
+}
+
+/// `s` truncated and then padded with spaces to exactly `cells` cells.
+pub fn pad(s: &str, cells: usize) -> String {
+    let mut out = truncate(s, cells);
+    let used = width(&out);
+    out.extend(std::iter::repeat_n(' ', cells.saturating_sub(used)));
+    out
+}